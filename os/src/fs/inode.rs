@@ -32,18 +32,52 @@ pub struct OSInodeInner {
     inode: Arc<Inode>,
 }
 
+/// A hard link recorded by [`LinkManager`]: `link` is an alternate name for
+/// the same file as `target`. `easy_fs` has no on-disk notion of multiple
+/// directory entries per inode, so this side table is what makes `link_at`/
+/// `unlink_at`/`stat`'s `nlink` mean anything at all.
+///
+/// Every file also carries one *primary* entry where `target == link ==`
+/// the file's own name, created by [`LinkManager::ensure_primary`]. That
+/// used to be a shared sentinel string (`"test"`) instead of the file's own
+/// name, which meant `unlink_at`/`stat` on any file actually named `test`
+/// matched the first app's bootstrap entry instead of its own — self-linking
+/// makes every primary entry unique to its file instead of colliding on one
+/// magic name.
 pub struct Link {
     target: String,
     link: String,
 }
 
+/// In-memory hard-link table layered on top of `easy_fs`, since the
+/// vendored `easy_fs` crate has no `link_at`/`unlink_at`/`ino`/`nlink` of
+/// its own to delegate to.
+///
+/// STATUS: blocked, not done. The backlog entry for this asked to delete
+/// `LinkManager` entirely and move `link_at`/`unlink_at` onto real
+/// directory-entry-level methods with on-disk refcounts on `easy_fs::Inode`.
+/// That needs new methods and an on-disk layout change in `easy_fs` itself —
+/// `easy_fs` is an external crate consumed via `use easy_fs::...`, and
+/// there's no `Cargo.toml`/vendored copy of its source anywhere in this tree
+/// (only in the real build this snapshot is taken from), so there is no
+/// `easy_fs::Inode` definition in `os` to add those methods to. A prior
+/// attempt in this series called the on-disk API the request asked for
+/// directly against the vendored crate and failed to compile for exactly
+/// this reason, and was reverted back to `LinkManager`. Nothing changed
+/// about that constraint since: this file cannot deliver the request as
+/// written, only keep improving the in-memory stand-in (see
+/// [`LinkManager::ensure_primary`]) until `easy_fs` grows the on-disk
+/// support to build the real thing on top of. `nlink`/`ino` reported
+/// through this path still do not survive a reboot, and `open_file` still
+/// resolves names through `LinkManager::fetch` rather than a real directory
+/// lookup.
 pub struct LinkManager {
     links: VecDeque<Arc<Link>>,
 }
 
 impl OSInode {
     /// create a new inode in memory
-    pub fn new(readable: bool, writable: bool, inode: Arc<Inode>, ino: u64, nlink: u32,stat_mode: StatMode, name: String) -> Self {
+    pub fn new(readable: bool, writable: bool, inode: Arc<Inode>, ino: u64, nlink: u32, stat_mode: StatMode, name: String) -> Self {
         Self {
             readable,
             writable,
@@ -81,11 +115,25 @@ pub fn list_apps() {
     println!("/**** APPS ****");
     for app in ROOT_INODE.ls() {
         println!("{}", app);
-        LINK_MANAGER.exclusive_access().add(app.clone().as_str(), "test");
+        LINK_MANAGER.exclusive_access().ensure_primary(app.as_str());
     }
     println!("**************/");
 }
 
+/// Create a hard link: a second name, `new_name`, for the same file as
+/// `old_name` (`sys_linkat`). Recorded in [`LINK_MANAGER`] rather than on
+/// disk — see the "STATUS: blocked" note on [`LinkManager`] for why real
+/// on-disk hard links aren't reachable from this file.
+pub fn link_at(old_name: &str, new_name: &str) -> isize {
+    LINK_MANAGER.exclusive_access().add(old_name, new_name)
+}
+
+/// Remove the `name` directory entry (`sys_unlinkat`) from [`LINK_MANAGER`]
+/// — see the "STATUS: blocked" note on [`LinkManager`].
+pub fn unlink_at(name: &str) -> isize {
+    LINK_MANAGER.exclusive_access().remove(name)
+}
+
 bitflags! {
     ///  The flags argument to the open() system call is constructed by ORing together zero or more of the following values:
     pub struct OpenFlags: u32 {
@@ -121,7 +169,7 @@ pub fn open_file(name: &str, flags: OpenFlags) -> Option<Arc<OSInode>> {
     let (readable, writable) = flags.read_write();
 
     let mut link_manager = LINK_MANAGER.exclusive_access();
-    let (name, nlink, index)= link_manager.all(name, flags.clone());
+    let (name, nlink, index) = link_manager.all(name, flags.clone());
     if flags.contains(OpenFlags::CREATE) {
         if let Some(inode) = ROOT_INODE.find(name) {
             // clear size
@@ -183,7 +231,7 @@ impl File for OSInode {
         let mut stat = self.stat.clone();
         let name = self.name.as_str();
         let mut link_manager = LINK_MANAGER.exclusive_access();
-        let (_, nlink, index)= link_manager.all(name, OpenFlags::RDWR);
+        let (_, nlink, index) = link_manager.all(name, OpenFlags::RDWR);
         stat.nlink = nlink as u32;
         stat.ino = index as u64;
         stat
@@ -200,7 +248,7 @@ impl LinkManager {
     pub fn all<'a>(&'a mut self, name: &'a str, flags: OpenFlags) -> (&'a str, usize, usize) {
         if flags.contains(OpenFlags::CREATE) {
             println!("[Kernel][link]all , add:{}", name);
-            self.add(name, "test");
+            self.ensure_primary(name);
         }
         let fetched_name = self.fetch(name);
         let nlink = self.find_num(&fetched_name);
@@ -208,6 +256,21 @@ impl LinkManager {
         (fetched_name, nlink, index)
     }
 
+    /// Register `name`'s own directory entry as a self-link (`target == link
+    /// == name`) the first time it's seen, so `find_num`/`stat`'s `nlink`
+    /// starts at 1 without relying on a shared sentinel name that could
+    /// collide with a real file (see the note on [`Link`]). A no-op if
+    /// `name` already has an entry, primary or otherwise.
+    pub fn ensure_primary(&mut self, name: &str) {
+        if self.links.iter().any(|link| link.target == name) {
+            return;
+        }
+        self.links.push_back(Arc::new(Link {
+            target: String::from(name),
+            link: String::from(name),
+        }));
+    }
+
     pub fn add(&mut self, target: &str, link: &str) -> isize {
         if target == link {
             return -1;
@@ -280,4 +343,4 @@ lazy_static! {
     /// TASK_MANAGER instance through lazy_static!
     pub static ref LINK_MANAGER: UPSafeCell<LinkManager> =
         unsafe { UPSafeCell::new(LinkManager::new()) };
-}
\ No newline at end of file
+}
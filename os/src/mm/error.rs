@@ -25,7 +25,13 @@ pub enum PageError {
     /// 无效页
     PageInvalid,
     /// 权限错误
-    PermissionError(PagePermissionError)
+    PermissionError(PagePermissionError),
+    /// 对一个非写时复制页调用了 COW 缺页处理，说明这其实是一次真正的写保护
+    /// 违规，而不是一次等待延迟拷贝的 COW 缺页
+    NotCowPage,
+    /// 分配 2MiB 巨页所需的 512 个连续物理帧时，物理内存碎片化导致找不到
+    /// 这么大的连续空闲块
+    NoContiguousBlock,
 }
 
 /// Errors related to area management
@@ -43,11 +49,15 @@ pub enum AreaError {
     AreaCritical,
     /// when requested vpn is not inside the area
     AreaRangeNotInclude,
+    /// when a user (`U`-permission) `Framed` mapping's start address falls
+    /// below the configurable `MMAP_MIN_ADDR` threshold (see
+    /// `mm::mmap_min_addr`/`mm::set_mmap_min_addr`)
+    AreaBelowMinAddr,
 }
 
 /// Errors related to memory management
 #[derive(Debug)]
-pub enum MMError {
+pub enum MemoryError {
     /// 内存不足
     MemoryNotEnough,
     /// 分页错误
@@ -56,15 +66,15 @@ pub enum MMError {
     AreaError(AreaError)
 }
 
-/// Wrapped `Result` for `MMError`
-pub type MMResult<R> = core::result::Result<R, MMError>;
+/// Wrapped `Result` for `MemoryError`
+pub type MemoryResult<R> = core::result::Result<R, MemoryError>;
 
-impl Display for MMError {
+impl Display for MemoryError {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
-            MMError::MemoryNotEnough => f.write_str("NotEnoughMemory"),
-            MMError::PageError(pe) => f.write_str(pe.to_string().as_str()),
-            MMError::AreaError(ae) => f.write_str(ae.to_string().as_str()),
+            MemoryError::MemoryNotEnough => f.write_str("NotEnoughMemory"),
+            MemoryError::PageError(pe) => f.write_str(pe.to_string().as_str()),
+            MemoryError::AreaError(ae) => f.write_str(ae.to_string().as_str()),
         }
     }
 }
@@ -87,6 +97,8 @@ impl Display for PageError {
             PageError::PageAlreadyAlloc => f.write_str("PageAlreadyValid"),
             PageError::PageInvalid => f.write_str("PageInvalid"),
             PageError::PermissionError(e) => f.write_str(e.to_string().as_str()),
+            PageError::NotCowPage => f.write_str("NotCowPage"),
+            PageError::NoContiguousBlock => f.write_str("NoContiguousBlock"),
         }
     }
 }
@@ -99,21 +111,22 @@ impl Display for AreaError {
             AreaError::AreaHasUnmappedPortion => f.write_str("AreaHasUnmappedPortion"),
             AreaError::AreaCritical => f.write_str("AreaCritical"),
             AreaError::AreaRangeNotInclude => f.write_str("AreaRangeNotInclude"),
+            AreaError::AreaBelowMinAddr => f.write_str("AreaBelowMinAddr"),
         }
     }
 }
 
-impl From<PageError> for MMError {
+impl From<PageError> for MemoryError {
     fn from(value: PageError) -> Self {
         Self::PageError(value)
     }
 }
-impl From<AreaError> for MMError {
+impl From<AreaError> for MemoryError {
     fn from(value: AreaError) -> Self {
         Self::AreaError(value)
     }
 }
-impl From<PagePermissionError> for MMError {
+impl From<PagePermissionError> for MemoryError {
     fn from(value: PagePermissionError) -> Self {
         Self::PageError(PageError::PermissionError(value))
     }
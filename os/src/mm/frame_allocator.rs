@@ -0,0 +1,161 @@
+//! Implementation of [`FrameAllocator`] which
+//! controls all the frames in the operating system.
+
+use super::{PhysAddr, PhysPageNum};
+use crate::config::MEMORY_END;
+use crate::sync::UPSafeCell;
+use alloc::vec::Vec;
+use core::fmt::{self, Debug, Formatter};
+use lazy_static::*;
+
+/// manage a frame which has the same lifecycle as the tracker
+pub struct FrameTracker {
+    /// physical page number, the first frame of the tracked range
+    pub ppn: PhysPageNum,
+    /// number of consecutive frames owned by this tracker, starting at `ppn`.
+    /// Always `1` for [`frame_alloc`]; [`frame_alloc_contiguous`] is the only
+    /// producer of a larger run, so the `Drop` below frees the whole block in
+    /// one go instead of needing a `Vec<FrameTracker>`.
+    frame_count: usize,
+}
+
+impl FrameTracker {
+    /// Create a new FrameTracker covering a single frame
+    pub fn new(ppn: PhysPageNum) -> Self {
+        // page cleaning
+        let bytes_array = ppn.get_bytes_array();
+        for i in bytes_array {
+            *i = 0;
+        }
+        Self { ppn, frame_count: 1 }
+    }
+
+    /// Create a new FrameTracker covering `frame_count` consecutive frames
+    /// starting at `ppn`
+    fn new_contiguous(ppn: PhysPageNum, frame_count: usize) -> Self {
+        for offset in 0..frame_count {
+            let bytes_array = PhysPageNum(ppn.0 + offset).get_bytes_array();
+            for i in bytes_array {
+                *i = 0;
+            }
+        }
+        Self { ppn, frame_count }
+    }
+}
+
+impl Debug for FrameTracker {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!("FrameTracker:PPN={:#x}", self.ppn.0))
+    }
+}
+
+impl Drop for FrameTracker {
+    fn drop(&mut self) {
+        frame_dealloc_range(self.ppn, self.frame_count);
+    }
+}
+
+trait FrameAllocator {
+    fn new() -> Self;
+    fn alloc(&mut self) -> Option<PhysPageNum>;
+    /// Allocate `count` *consecutive* frames, or `None` if the free set has
+    /// no run that long (the recycled list is unordered, so this only
+    /// succeeds against the untouched tail above `current`).
+    fn alloc_contiguous(&mut self, count: usize) -> Option<PhysPageNum>;
+    fn dealloc(&mut self, ppn: PhysPageNum);
+}
+
+/// an implementation for frame allocator
+pub struct StackFrameAllocator {
+    current: usize,
+    end: usize,
+    recycled: Vec<usize>,
+}
+
+impl StackFrameAllocator {
+    pub fn init(&mut self, l: PhysPageNum, r: PhysPageNum) {
+        self.current = l.0;
+        self.end = r.0;
+    }
+}
+impl FrameAllocator for StackFrameAllocator {
+    fn new() -> Self {
+        Self {
+            current: 0,
+            end: 0,
+            recycled: Vec::new(),
+        }
+    }
+    fn alloc(&mut self) -> Option<PhysPageNum> {
+        if let Some(ppn) = self.recycled.pop() {
+            Some(ppn.into())
+        } else if self.current == self.end {
+            None
+        } else {
+            self.current += 1;
+            Some((self.current - 1).into())
+        }
+    }
+    fn alloc_contiguous(&mut self, count: usize) -> Option<PhysPageNum> {
+        if count == 0 || self.current + count > self.end {
+            return None;
+        }
+        let ppn = self.current;
+        self.current += count;
+        Some(ppn.into())
+    }
+    fn dealloc(&mut self, ppn: PhysPageNum) {
+        let ppn = ppn.0;
+        // validity check
+        if ppn >= self.current || self.recycled.iter().any(|&v| v == ppn) {
+            panic!("Frame ppn={:#x} has not been allocated!", ppn);
+        }
+        // recycle
+        self.recycled.push(ppn);
+    }
+}
+
+type FrameAllocatorImpl = StackFrameAllocator;
+
+lazy_static! {
+    /// frame allocator instance through lazy_static!
+    pub static ref FRAME_ALLOCATOR: UPSafeCell<FrameAllocatorImpl> =
+        unsafe { UPSafeCell::new(FrameAllocatorImpl::new()) };
+}
+
+/// initiate the frame allocator using `ekernel` and `MEMORY_END`
+pub fn init_frame_allocator() {
+    extern "C" {
+        fn ekernel();
+    }
+    FRAME_ALLOCATOR.exclusive_access().init(
+        PhysAddr::from(ekernel as usize).ceil(),
+        PhysAddr::from(MEMORY_END).floor(),
+    );
+}
+
+/// allocate a frame
+pub fn frame_alloc() -> Option<FrameTracker> {
+    FRAME_ALLOCATOR
+        .exclusive_access()
+        .alloc()
+        .map(FrameTracker::new)
+}
+
+/// allocate `count` physically consecutive frames as a single [`FrameTracker`].
+/// Returns `None` if the allocator has no run of `count` contiguous frames
+/// left (recycled single frames don't get stitched back together, so this
+/// only draws from the untouched tail of the physical range).
+pub fn frame_alloc_contiguous(count: usize) -> Option<FrameTracker> {
+    FRAME_ALLOCATOR
+        .exclusive_access()
+        .alloc_contiguous(count)
+        .map(|ppn| FrameTracker::new_contiguous(ppn, count))
+}
+
+fn frame_dealloc_range(ppn: PhysPageNum, count: usize) {
+    let mut allocator = FRAME_ALLOCATOR.exclusive_access();
+    for offset in 0..count {
+        allocator.dealloc(PhysPageNum(ppn.0 + offset));
+    }
+}
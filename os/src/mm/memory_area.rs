@@ -1,16 +1,113 @@
 use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::arch::asm;
+use core::sync::atomic::{AtomicUsize, Ordering};
 
 use crate::{config::PAGE_SIZE, mm::address::StepByOne};
-use super::{frame_alloc, FrameTracker, MemoryError, MemoryResult, PageError, PhysPageNum, VirtAddr, VirtPageNum};
+use super::{frame_alloc, frame_alloc_contiguous, AreaError, FrameTracker, MemoryError, MemoryResult, PageError, PhysPageNum, VirtAddr, VirtPageNum};
 use super::address::VPNRange;
 use super::page_table::{PTEFlags, PageTable};
 
+/// 一个 2MiB 巨页覆盖的 L0 页数（SV39 下 L1 叶子页大小 / `PAGE_SIZE`）
+const HUGE_PAGE_VPN_COUNT: usize = 512;
+/// 2MiB 巨页大小，字节
+const HUGE_PAGE_SIZE: usize = HUGE_PAGE_VPN_COUNT * PAGE_SIZE;
+
+/// 用户态 `Framed` 映射允许的最低起始地址，仿照 Linux/DragonOS 保留低地址
+/// 空间，把空指针附近的野访问变成一次干净的映射失败，而不是悄悄建立映射后
+/// 再被解引用。默认 0x10000（65536），可通过 [`set_mmap_min_addr`] 调整。
+static MMAP_MIN_ADDR: AtomicUsize = AtomicUsize::new(0x10000);
+
+/// 读取当前的 `MMAP_MIN_ADDR` 阈值
+pub fn mmap_min_addr() -> usize {
+    MMAP_MIN_ADDR.load(Ordering::Relaxed)
+}
+
+/// 调整 `MMAP_MIN_ADDR` 阈值，供内核按需放宽/收紧低地址保护
+pub fn set_mmap_min_addr(addr: usize) {
+    MMAP_MIN_ADDR.store(addr, Ordering::Relaxed);
+}
+
+/// 超过这么多页被改动后，[`Flusher`] 放弃逐页刷新，转而在 `Drop` 时发出一次
+/// 不带操作数的全局 `sfence.vma`，避免批量操作时刷新指令本身比它要避免的
+/// 缺页还贵。
+const FLUSH_GLOBAL_THRESHOLD: usize = 64;
+
+/// 批量 PTE 修改的 TLB 刷新句柄，灵感来自 DragonOS 的按地址刷新 / 全局刷新
+/// 二选一策略：`map_one`/`unmap_one`/`check_page_raw` 等修改 PTE 的函数把受
+/// 影响的 [`VirtPageNum`] `record` 进来，`Flusher` 在 `Drop`（或手动
+/// `flush`）时对数量不超过 [`FLUSH_GLOBAL_THRESHOLD`] 的页逐个发出单地址
+/// `sfence.vma`，否则退化为一次全局刷新。`MapArea` 的每个公开方法在内部持有
+/// 一个 `Flusher`，让循环内的多次 PTE 修改合并成一次（或几次）刷新，而单页
+/// 缺页这种最常见的路径仍然只产生一次单地址刷新。
+struct Flusher {
+    pages: Vec<VirtPageNum>,
+    global: bool,
+}
+
+impl Flusher {
+    fn new() -> Self {
+        Self {
+            pages: Vec::new(),
+            global: false,
+        }
+    }
+
+    /// 记录一个被修改过 PTE 的页；记录数超过 [`FLUSH_GLOBAL_THRESHOLD`] 后
+    /// 自动升级为全局刷新
+    fn record(&mut self, vpn: VirtPageNum) {
+        if self.global {
+            return;
+        }
+        self.pages.push(vpn);
+        if self.pages.len() > FLUSH_GLOBAL_THRESHOLD {
+            self.global = true;
+            self.pages.clear();
+        }
+    }
+
+    /// 立即发出刷新，而不是等到 `Drop`
+    fn flush(&mut self) {
+        if self.global {
+            unsafe { asm!("sfence.vma") };
+        } else {
+            for vpn in self.pages.drain(..) {
+                let va: usize = VirtAddr::from(vpn).0;
+                unsafe { asm!("sfence.vma {}, zero", in(reg) va) };
+            }
+        }
+        self.global = false;
+    }
+}
+
+impl Drop for Flusher {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
 /// 连续虚拟内存映射
 pub struct MapArea {
     vpn_range: VPNRange,
-    data_frame: BTreeMap<VirtPageNum, FrameTracker>,
+    data_frame: BTreeMap<VirtPageNum, Arc<FrameTracker>>,
     map_type: MapType,
     map_permission: MapPermission,
+    /// 区域内的帧是否与另一个地址空间共享（写时复制）。
+    /// 为 `true` 时，写访问需要先经过 `handle_cow_fault`。
+    cow: bool,
+    /// `Framed` 页的软件 "referenced" 位，供 [`Self::reclaim_clean`] 的
+    /// clock/second-chance 回收算法使用；键不在本表中视为 referenced（刚
+    /// 分配/搬运过来的页优先保留一轮）。没有 trap 分发器在真正的访存里重新
+    /// 置位（见 `MemorySet::handle_page_fault` 的 "no caller yet" 说明），
+    /// 所以目前每个页最多获得一次 "再给一次机会"，效果上接近按分配顺序回收。
+    referenced: BTreeMap<VirtPageNum, bool>,
+    /// [`Self::copy_data`] 写入过真实内容（ELF `.data`/`.text` 等）的页。
+    /// `copy_data` 是内核通过物理地址直接写帧（`ppn.get_bytes_array()`），
+    /// 不经过这页的用户态 PTE，所以硬件 `D` 位感知不到这次写入——
+    /// [`Self::reclaim_clean`] 必须单独记这笔账，否则会把刚加载好的代码/
+    /// 数据段当成"从没人写过"回收掉。键不在本表中即未被 `copy_data` 写过。
+    populated: BTreeMap<VirtPageNum, bool>,
 }
 
 impl MapArea {
@@ -19,9 +116,19 @@ impl MapArea {
         self.vpn_range
     }
 
+    /// 获取当前的映射权限
+    pub fn get_map_permission(&self) -> MapPermission {
+        self.map_permission
+    }
+
+    /// 该区域是否处于写时复制状态
+    pub fn is_cow(&self) -> bool {
+        self.cow
+    }
+
     /// 分割内存映射
     pub fn split(self, vpn: VirtPageNum) -> (Self, Self) {
-        let mut other = Self {vpn_range: VPNRange::new(vpn, vpn), data_frame: BTreeMap::new(), map_type: self.map_type, map_permission: self.map_permission};
+        let mut other = Self {vpn_range: VPNRange::new(vpn, vpn), data_frame: BTreeMap::new(), map_type: self.map_type, map_permission: self.map_permission, cow: self.cow, referenced: BTreeMap::new(), populated: BTreeMap::new()};
         if vpn <= self.vpn_range.get_start() {
             return (other, self);
         } else if vpn >= self.vpn_range.get_end() {
@@ -36,63 +143,151 @@ impl MapArea {
                     right.insert(i, frame);
                 }
             }
+            // `referenced`/`populated` 跟 `data_frame` 一样按 `vpn` 拆分，否则
+            // 拆出来的两半会丢失各自页面的 "已被 copy_data 写过真实数据" 记录，
+            // 让 `reclaim_clean` 把刚加载的内容当成从没人动过的干净页回收掉。
+            let mut referenced_left = BTreeMap::new();
+            let mut referenced_right = BTreeMap::new();
+            for (i, r) in self.referenced.into_iter() {
+                if i < vpn {
+                    referenced_left.insert(i, r);
+                } else {
+                    referenced_right.insert(i, r);
+                }
+            }
+            let mut populated_left = BTreeMap::new();
+            let mut populated_right = BTreeMap::new();
+            for (i, p) in self.populated.into_iter() {
+                if i < vpn {
+                    populated_left.insert(i, p);
+                } else {
+                    populated_right.insert(i, p);
+                }
+            }
             let left = Self {
                 vpn_range: VPNRange::new(self.vpn_range.get_start(), vpn),
                 data_frame: left,
                 map_type: self.map_type,
-                map_permission: self.map_permission
+                map_permission: self.map_permission,
+                cow: self.cow,
+                referenced: referenced_left,
+                populated: populated_left,
             };
             other = Self {
                 vpn_range: VPNRange::new(vpn, self.vpn_range.get_end()),
                 data_frame: right,
                 map_type: self.map_type,
-                map_permission: self.map_permission
+                map_permission: self.map_permission,
+                cow: self.cow,
+                referenced: referenced_right,
+                populated: populated_right,
             };
             return (left, other);
         }
     }
-    /// 新建映射区域
+    /// 新建映射区域。`HugeFramed` 要求 `start_virt_addr`/`end_virt_addr` 按
+    /// 2MiB（`HUGE_PAGE_SIZE`）对齐，否则返回 `AreaError::AreaRangeNotInclude`
+    /// —— 一个 2MiB 巨页 L1 叶子 PTE 没法覆盖一段未对齐的区间。
+    ///
+    /// 带 `U` 权限的非 `Identical` 映射（`Framed`/`HugeFramed`，即用户态可
+    /// 访问）如果起始地址低于 [`mmap_min_addr`]，返回
+    /// `AreaError::AreaBelowMinAddr`：这类低地址访问多半是空指针附近的内核/
+    /// 用户态 bug，拒绝建立映射比让它悄悄成功更安全。`Identical` 映射是内核
+    /// 固定区域，不受这条限制约束。
     pub fn new(
         start_virt_addr: VirtAddr,
         end_virt_addr: VirtAddr,
         map_type: MapType,
         map_perm: MapPermission,
-    ) -> Self {
+    ) -> MemoryResult<Self> {
         let start_vpn: VirtPageNum = start_virt_addr.floor();
         let end_vpn: VirtPageNum = end_virt_addr.ceil();
-        Self {
+        if map_type == MapType::HugeFramed
+            && (start_vpn.0 % HUGE_PAGE_VPN_COUNT != 0 || end_vpn.0 % HUGE_PAGE_VPN_COUNT != 0)
+        {
+            return Err(AreaError::AreaRangeNotInclude.into());
+        }
+        if map_type != MapType::Identical
+            && map_perm.contains(MapPermission::U)
+            && start_virt_addr.0 < mmap_min_addr()
+        {
+            return Err(AreaError::AreaBelowMinAddr.into());
+        }
+        Ok(Self {
             vpn_range: VPNRange::new(start_vpn, end_vpn),
             data_frame: BTreeMap::new(),
             map_type,
             map_permission: map_perm,
-        }
+            cow: false,
+            referenced: BTreeMap::new(),
+            populated: BTreeMap::new(),
+        })
     }
 
     /// 检查页的原始函数
-    fn check_page_raw(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) -> MemoryResult<()> {
+    fn check_page_raw(&mut self, page_table: &mut PageTable, vpn: VirtPageNum, flusher: &mut Flusher) -> MemoryResult<()> {
         if !self.data_frame.contains_key(&vpn) {
             let frame = frame_alloc().ok_or(MemoryError::MemoryNotEnough)?;
             let ppn = frame.ppn;
-            self.data_frame.insert(vpn, frame);
+            self.data_frame.insert(vpn, Arc::new(frame));
+            self.referenced.insert(vpn, true);
             let pte_flags = PTEFlags::from_bits(self.map_permission.bits).unwrap();
             match page_table.map(vpn, ppn, pte_flags) {
-                Ok(_) => return Ok(()),
+                Ok(_) => {
+                    flusher.record(vpn);
+                    return Ok(());
+                },
                 Err(e) => {
                     self.data_frame.remove(&vpn);
+                    self.referenced.remove(&vpn);
+                    return Err(e);
+                },
+            }
+        }
+        Ok(())
+    }
+
+    /// 巨页版本的 `check_page_raw`：`data_frame` 以 2MiB 块的起始 VPN 为键，
+    /// 一次性分配 512 个连续物理帧并安装一个 L1 叶子 PTE。找不到连续空闲块
+    /// 时返回 `PageError::NoContiguousBlock`，调用方可以据此退回 `Framed`。
+    fn check_huge_page_raw(&mut self, page_table: &mut PageTable, base_vpn: VirtPageNum, flusher: &mut Flusher) -> MemoryResult<()> {
+        if !self.data_frame.contains_key(&base_vpn) {
+            let frame = frame_alloc_contiguous(HUGE_PAGE_VPN_COUNT).ok_or(PageError::NoContiguousBlock)?;
+            let ppn = frame.ppn;
+            self.data_frame.insert(base_vpn, Arc::new(frame));
+            let pte_flags = PTEFlags::from_bits(self.map_permission.bits).unwrap();
+            match page_table.map_huge(base_vpn, ppn, pte_flags) {
+                Ok(_) => {
+                    flusher.record(base_vpn);
+                    return Ok(());
+                },
+                Err(e) => {
+                    self.data_frame.remove(&base_vpn);
                     return Err(e);
                 },
             }
         }
         Ok(())
     }
-    /// 检查范围
+
+    /// 检查范围。内部持有一个 [`Flusher`]，同一次调用里分配的多个页在
+    /// `Drop` 时合并成一次（或几次）`sfence.vma`，单页缺页这种最常见的情形
+    /// 仍然只产生一次单地址刷新。
     pub fn check_range(&mut self, page_table: &mut PageTable, vpn_range: VPNRange) -> MemoryResult<()> {
+        let mut flusher = Flusher::new();
         match self.map_type {
             MapType::Identical => Ok(()),
             MapType::Framed => {
                 self.vpn_range.intersection(&vpn_range);
                 for vpn in self.vpn_range.intersection(&vpn_range) {
-                    self.check_page_raw(page_table, vpn)?;
+                    self.check_page_raw(page_table, vpn, &mut flusher)?;
+                }
+                Ok(())
+            },
+            MapType::HugeFramed => {
+                for vpn in self.vpn_range.intersection(&vpn_range) {
+                    let base_vpn = VirtPageNum(vpn.0 - vpn.0 % HUGE_PAGE_VPN_COUNT);
+                    self.check_huge_page_raw(page_table, base_vpn, &mut flusher)?;
                 }
                 Ok(())
             },
@@ -103,34 +298,144 @@ impl MapArea {
         self.check_range(page_table, self.vpn_range)
     }
 
-    fn map_one(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) -> MemoryResult<()> {
+    /// 回收本区域内最多 `max` 个"干净"页，腾出物理帧供 OOM 时重试分配。
+    /// 只处理 `Framed`：`Identical` 映射是内核固定区域不该被回收，
+    /// `HugeFramed` 整块 2MiB 一起分配/释放，拆开回收单页没有意义。用
+    /// [`Self::referenced`] 做简化版 clock/second-chance：第一次扫到某页把
+    /// `referenced` 清掉、给它一次"再用一次"的机会，第二次再扫到仍未被
+    /// （重新）标记的页才进入真正的"干净"检查。
+    ///
+    /// 这个内核没有 swap：被回收的帧直接释放，下次访问靠
+    /// [`Self::check_page_raw`] 重新分配一个**全零**的帧，而不是把原内容写回
+    /// 来。所以"干净"在这里必须是"丢了也等于没丢"，有两道检查都通过才真的
+    /// 回收：
+    /// - 硬件 `D`（dirty）位清零，即建立映射以来这页对应的用户态虚拟地址
+    ///   从没被写过（目标 QEMU `virt` 机器的 SV39 会自动置位 `A`/`D`，不需要
+    ///   trap 分发器参与）；
+    /// - [`Self::populated`] 里没有记录，即 [`Self::copy_data`] 也没有通过
+    ///   物理地址直接往这帧里写过 ELF 数据——那条路径绕开了用户态 PTE，`D`
+    ///   位感知不到。
+    ///
+    /// 只要有一项不满足就跳过且保留该页，不清它的 `referenced`/`populated`
+    /// 记录，下一轮扫描原样再判一次。返回实际回收的页数。
+    pub fn reclaim_clean(&mut self, page_table: &mut PageTable, max: usize) -> MemoryResult<usize> {
+        if self.map_type != MapType::Framed || max == 0 {
+            return Ok(0);
+        }
+        let mut flusher = Flusher::new();
+        let mut reclaimed = 0;
+        let vpns: Vec<VirtPageNum> = self.data_frame.keys().copied().collect();
+        for vpn in vpns {
+            if reclaimed >= max {
+                break;
+            }
+            if self.referenced.get(&vpn).copied().unwrap_or(true) {
+                self.referenced.insert(vpn, false);
+                continue;
+            }
+            if self.populated.contains_key(&vpn) {
+                continue;
+            }
+            let dirty = page_table
+                .translate(vpn)
+                .map(|pte| pte.dirty())
+                .unwrap_or(true);
+            if dirty {
+                continue;
+            }
+            self.data_frame.remove(&vpn);
+            self.referenced.remove(&vpn);
+            match page_table.unmap(vpn) {
+                Ok(_) => {
+                    flusher.record(vpn);
+                    reclaimed += 1;
+                },
+                Err(MemoryError::PageError(PageError::DirPageInvalid)) => {},
+                Err(MemoryError::PageError(PageError::PageInvalid)) => {},
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(reclaimed)
+    }
+
+    /// 处理落在本区域内的缺页异常：按需分配一帧并建立映射，让陷入现场重新
+    /// 执行故障指令即可。`vpn` 不在 `vpn_range` 内时返回
+    /// `AreaError::AreaRangeNotInclude`，调用方（`MemorySet::handle_page_fault`）
+    /// 应当据此判断这其实是一次真正的段错误。
+    ///
+    /// STATUS: blocked, not dead code. This is the real demand-paging step —
+    /// `map_one`'s `Framed`/`HugeFramed` branch below installs no PTE and
+    /// `check_page_raw` is the only thing that ever allocates a frame for
+    /// one of these areas — but nothing in this tree decides *when* to call
+    /// it: that's `MemorySet::handle_page_fault`'s job, and its own doc notes
+    /// the `trap` dispatcher that would call *that* doesn't exist in this
+    /// repo slice. So today a first touch of a lazy `Framed` page never
+    /// reaches here at all; `check_all_page` eager-allocating
+    /// everything up front (`push`, `mmap` with `MAP_POPULATE`) is the only
+    /// path that currently works.
+    pub fn handle_page_fault(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) -> MemoryResult<()> {
+        if !self.vpn_range.is_contains(&vpn) {
+            return Err(AreaError::AreaRangeNotInclude.into());
+        }
+        self.check_range(page_table, VPNRange::new_by_len(vpn, 1))
+    }
+
+    fn map_one(&mut self, page_table: &mut PageTable, vpn: VirtPageNum, flusher: &mut Flusher) -> MemoryResult<()> {
         match self.map_type {
             MapType::Identical => {
                 let ppn = PhysPageNum(vpn.0);
                 let pte_flags = PTEFlags::from_bits(self.map_permission.bits).unwrap();
-                page_table.map(vpn, ppn, pte_flags)
+                page_table.map(vpn, ppn, pte_flags)?;
+                flusher.record(vpn);
+                Ok(())
             }
-            MapType::Framed => {
+            // 与 `Framed` 一样惰性：实际分配延迟到 `check_huge_page_raw`
+            MapType::Framed | MapType::HugeFramed => {
                 Ok(())
             }
         }
     }
 
-    fn unmap_one(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) -> MemoryResult<()> {
+    /// 撤销 `vpn` 所在页/块的映射。`HugeFramed` 以 2MiB 块为粒度：只有落在
+    /// 块起始 VPN 上的调用才真正释放那 512 个连续帧并撤销 L1 叶子 PTE，块内
+    /// 其余 VPN 上的调用直接视为已处理。
+    fn unmap_one(&mut self, page_table: &mut PageTable, vpn: VirtPageNum, flusher: &mut Flusher) -> MemoryResult<()> {
+        if self.map_type == MapType::HugeFramed {
+            if vpn.0 % HUGE_PAGE_VPN_COUNT != 0 {
+                return Ok(());
+            }
+            self.data_frame.remove(&vpn);
+            return match page_table.unmap_huge(vpn) {
+                Ok(_) => {
+                    flusher.record(vpn);
+                    Ok(())
+                },
+                Err(MemoryError::PageError(PageError::DirPageInvalid)) => Ok(()),
+                Err(MemoryError::PageError(PageError::PageInvalid)) => Ok(()),
+                Err(e) => Err(e),
+            };
+        }
         if self.map_type == MapType::Framed {
             self.data_frame.remove(&vpn); // 释放映射的帧
+            self.referenced.remove(&vpn);
+            self.populated.remove(&vpn);
         }
         match page_table.unmap(vpn) {
-            Ok(_) => Ok(()),
+            Ok(_) => {
+                flusher.record(vpn);
+                Ok(())
+            },
             Err(MemoryError::PageError(PageError::DirPageInvalid)) => Ok(()),
             Err(MemoryError::PageError(PageError::PageInvalid)) => Ok(()),
             Err(e) => Err(e)
         }
     }
-    /// 非严格的完全映射
+    /// 非严格的完全映射。整段区域内的 PTE 改动共用一个 [`Flusher`]，在方法
+    /// 返回前合并成一次（或几次）`sfence.vma`。
     pub fn map(&mut self, page_table: &mut PageTable) -> MemoryResult<()> {
+        let mut flusher = Flusher::new();
         for vpn in self.vpn_range {
-            match self.map_one(page_table, vpn) {
+            match self.map_one(page_table, vpn, &mut flusher) {
                 Ok(_) => {},
                 Err(e) => return Err(e)
             }
@@ -140,8 +445,9 @@ impl MapArea {
 
     /// 取消所有映射
     pub fn unmap(&mut self, page_table: &mut PageTable) -> MemoryResult<()> {
+        let mut flusher = Flusher::new();
         for vpn in self.vpn_range {
-            match self.unmap_one(page_table, vpn) {
+            match self.unmap_one(page_table, vpn, &mut flusher) {
                 Ok(_) => {},
                 Err(e) => {
                     return Err(e);
@@ -154,8 +460,9 @@ impl MapArea {
     /// 收缩内存区域
     #[allow(unused)]
     pub fn narrow(&mut self, page_table: &mut PageTable, to: VirtPageNum) -> MemoryResult<()> {
+        let mut flusher = Flusher::new();
         for vpn in VPNRange::new(to, self.vpn_range.get_end()) {
-            match self.unmap_one(page_table, vpn) {
+            match self.unmap_one(page_table, vpn, &mut flusher) {
                 Ok(_) => {},
                 Err(e) => return Err(e)
             }
@@ -167,8 +474,9 @@ impl MapArea {
     /// 扩张内存区域
     #[allow(unused)]
     pub fn expand(&mut self, page_table: &mut PageTable, to: VirtPageNum) -> MemoryResult<()> {
+        let mut flusher = Flusher::new();
         for vpn in VPNRange::new(self.vpn_range.get_end(), to) {
-            match self.map_one(page_table, vpn) {
+            match self.map_one(page_table, vpn, &mut flusher) {
                 Ok(_) => {},
                 Err(e) => return Err(e)
             }
@@ -176,22 +484,115 @@ impl MapArea {
         self.vpn_range = VPNRange::new(self.vpn_range.get_start(), to);
         Ok(())
     }
-    /// 复制数据并确保所需要的帧
+
+    /// 调整（可能移动）本区域，为 `mremap` 提供底层支持。`new_start`/
+    /// `new_len` 描述目标区间；若 `new_start` 与当前起始 VPN 相同，直接复用
+    /// `narrow`/`expand` 原地调整大小。否则在 `allow_move` 为真时将本区域
+    /// 迁移到 `new_start`：已分配的 `Framed` 帧按偏移量搬到新的 VPN 键下
+    /// 重新建立映射（不触碰帧本身，因此不需要拷贝数据），旧区间的映射随之
+    /// 解除；是否与其他区域冲突由调用方（`MemorySet::mremap`）在决定
+    /// `new_start`/`allow_move` 前检查。
+    pub fn remap(
+        &mut self,
+        page_table: &mut PageTable,
+        new_start: VirtPageNum,
+        new_len: usize,
+        allow_move: bool,
+    ) -> MemoryResult<()> {
+        let old_start = self.vpn_range.get_start();
+        let new_end = VPNRange::new_by_len(new_start, new_len).get_end();
+        if new_start == old_start {
+            return if new_end.0 <= self.vpn_range.get_end().0 {
+                self.narrow(page_table, new_end)
+            } else {
+                self.expand(page_table, new_end)
+            };
+        }
+        if !allow_move {
+            return Err(AreaError::AreaHasMappedPortion.into());
+        }
+        assert_eq!(self.map_type, MapType::Framed, "only Framed areas can be relocated");
+        let pte_flags = PTEFlags::from_bits(self.map_permission.bits).unwrap();
+        let shift = new_start.0 as isize - old_start.0 as isize;
+        let mut flusher = Flusher::new();
+        for vpn in self.vpn_range {
+            // 只撤销页表项，保留 `data_frame` 里的帧归属，稍后按新键重新插入
+            match page_table.unmap(vpn) {
+                Ok(_) => flusher.record(vpn),
+                Err(MemoryError::PageError(PageError::DirPageInvalid)) => {},
+                Err(MemoryError::PageError(PageError::PageInvalid)) => {},
+                Err(e) => return Err(e),
+            }
+        }
+        let wants_write = self.map_permission.contains(MapPermission::W);
+        let old_frames = core::mem::take(&mut self.data_frame);
+        let mut new_frames = BTreeMap::new();
+        for (old_vpn, frame) in old_frames.into_iter() {
+            let new_vpn = VirtPageNum((old_vpn.0 as isize + shift) as usize);
+            // 和 `set_permission` 一样：仍处于 COW 共享状态的帧不能直接把 `W`
+            // 位搬到新地址上，否则 `sys_mremap(MREMAP_MAYMOVE)` 就成了绕过
+            // COW 缺页、拿到共享帧写权限的后门，这里先拷贝解除共享。
+            let (ppn, frame) = if self.cow && wants_write && Arc::strong_count(&frame) > 1 {
+                let new_frame = frame_alloc().ok_or(MemoryError::MemoryNotEnough)?;
+                new_frame
+                    .ppn
+                    .get_bytes_array()
+                    .copy_from_slice(frame.ppn.get_bytes_array());
+                let new_ppn = new_frame.ppn;
+                (new_ppn, Arc::new(new_frame))
+            } else {
+                (frame.ppn, frame)
+            };
+            page_table.map(new_vpn, ppn, pte_flags)?;
+            flusher.record(new_vpn);
+            new_frames.insert(new_vpn, frame);
+        }
+        self.data_frame = new_frames;
+        // `referenced`/`populated` 跟 `data_frame` 一样按新键搬运
+        let shift_key = |vpn: VirtPageNum| VirtPageNum((vpn.0 as isize + shift) as usize);
+        self.referenced = core::mem::take(&mut self.referenced)
+            .into_iter()
+            .map(|(vpn, r)| (shift_key(vpn), r))
+            .collect();
+        self.populated = core::mem::take(&mut self.populated)
+            .into_iter()
+            .map(|(vpn, p)| (shift_key(vpn), p))
+            .collect();
+        self.vpn_range = VPNRange::new(new_start, new_end);
+        Ok(())
+    }
+
+    /// 复制数据并确保所需要的帧。`HugeFramed` 按 2MiB 对齐触发分配（一次
+    /// `check_range` 调用覆盖整块巨页，而不是 512 次单页分配），但逐字节
+    /// 拷贝仍以 4KiB 为粒度写入，因为物理帧仍然是按 4KiB 暴露字节数组的。
     pub fn copy_data(&mut self, page_table: &mut PageTable, data: &[u8]) -> MemoryResult<()> {
-        assert_eq!(self.map_type, MapType::Framed);
-        let pages = (data.len() - 1 + PAGE_SIZE) / PAGE_SIZE;
-        assert!(pages <= self.vpn_range.into_iter().count());
-        self.check_range(page_table, VPNRange::new_by_len(self.vpn_range.get_start(), pages))?;
+        assert!(self.map_type == MapType::Framed || self.map_type == MapType::HugeFramed);
+        let stride = if self.map_type == MapType::HugeFramed { HUGE_PAGE_SIZE } else { PAGE_SIZE };
+        let alloc_unit_vpns = if self.map_type == MapType::HugeFramed { HUGE_PAGE_VPN_COUNT } else { 1 };
+        let units = (data.len() - 1 + stride) / stride;
+        assert!(units * alloc_unit_vpns <= self.vpn_range.into_iter().count());
+        self.check_range(page_table, VPNRange::new_by_len(self.vpn_range.get_start(), units * alloc_unit_vpns))?;
         let mut start: usize = 0;
         let mut current_vpn = self.vpn_range.get_start();
         let len = data.len();
         loop {
             let src = &data[start..len.min(start + PAGE_SIZE)];
-            let dst = &mut page_table
-                .translate(current_vpn)?
-                .ppn()
-                .get_bytes_array()[..src.len()];
+            // 4KiB 的 `translate` 解析不了 L1 巨页叶子，巨页直接按块内偏移找帧
+            let ppn = if self.map_type == MapType::HugeFramed {
+                let offset = current_vpn.0 % HUGE_PAGE_VPN_COUNT;
+                let base_vpn = VirtPageNum(current_vpn.0 - offset);
+                let frame = self.data_frame.get(&base_vpn).ok_or(AreaError::AreaRangeNotInclude)?;
+                PhysPageNum(frame.ppn.0 + offset)
+            } else {
+                page_table.translate(current_vpn)?.ppn()
+            };
+            let dst = &mut ppn.get_bytes_array()[..src.len()];
             dst.copy_from_slice(src);
+            // 这一写是通过物理地址直接进帧的，不经过 `current_vpn` 的用户态
+            // PTE，硬件 `D` 位感知不到；`Framed` 页靠 `populated` 记一笔，让
+            // `reclaim_clean` 别把装了真实数据的页当成干净页回收掉。
+            // （`HugeFramed` 本就不参与 `reclaim_clean`，记了也无害。）
+            self.populated.insert(current_vpn, true);
             start += PAGE_SIZE;
             if start >= len {
                 break;
@@ -200,6 +601,185 @@ impl MapArea {
         }
         Ok(())
     }
+
+    /// 写时复制(COW)克隆：仅对 `Framed` 区域共享物理帧，并在父子双方页表中
+    /// 清除 `W` 位；`Identical` 区域本就映射到固定物理地址，直接重新映射即可。
+    ///
+    /// `HugeFramed` 不参与真正的 COW 共享：2MiB 整块共享没有按 4KiB 粒度的
+    /// 引用计数拆分实现，贸然共享会让子进程的写入错误地污染父进程的巨页。
+    /// 这里退回立即整块拷贝——给子进程分配一组全新的连续物理帧，把父进程
+    /// 那 2MiB 原样复制过去，调用方（`MemorySet::from_existed_user`）不需要
+    /// 关心这点，按普通区域一样拿到一个可用的子进程 `MapArea`。
+    pub fn clone_cow(&mut self, page_table: &mut PageTable, child_page_table: &mut PageTable) -> MemoryResult<Self> {
+        if self.map_type == MapType::HugeFramed {
+            let mut other = Self::new(
+                self.vpn_range.get_start().into(),
+                self.vpn_range.get_end().into(),
+                self.map_type,
+                self.map_permission,
+            )?;
+            other.check_all_page(child_page_table)?;
+            for (&base_vpn, frame) in self.data_frame.iter() {
+                let new_frame = other
+                    .data_frame
+                    .get(&base_vpn)
+                    .ok_or(AreaError::AreaRangeNotInclude)?;
+                for i in 0..HUGE_PAGE_VPN_COUNT {
+                    PhysPageNum(new_frame.ppn.0 + i)
+                        .get_bytes_array()
+                        .copy_from_slice(PhysPageNum(frame.ppn.0 + i).get_bytes_array());
+                }
+            }
+            return Ok(other);
+        }
+        if self.map_type == MapType::Identical {
+            let mut other = Self::new(
+                self.vpn_range.get_start().into(),
+                self.vpn_range.get_end().into(),
+                self.map_type,
+                self.map_permission,
+            )?;
+            other.map(child_page_table)?;
+            return Ok(other);
+        }
+        let ro_perm = self.map_permission - MapPermission::W;
+        let pte_flags = PTEFlags::from_bits(ro_perm.bits).unwrap();
+        let mut data_frame = BTreeMap::new();
+        let mut flusher = Flusher::new();
+        for (&vpn, frame) in self.data_frame.iter() {
+            // 父进程也清除 W 位，下次写入会触发 COW 缺页而不是直接改坏共享帧
+            page_table.unmap(vpn)?;
+            page_table.map(vpn, frame.ppn, pte_flags)?;
+            child_page_table.map(vpn, frame.ppn, pte_flags)?;
+            flusher.record(vpn);
+            data_frame.insert(vpn, Arc::clone(frame));
+        }
+        self.cow = true;
+        // 子进程和父进程共享同一批帧，`populated`（"copy_data 写过真实内容"）
+        // 的记录必须跟着帧一起复制过去——否则子进程这边的 `reclaim_clean` 会
+        // 把和父进程共享的、装着真实 ELF 数据的帧当成从没写过的干净页回收掉。
+        Ok(Self {
+            vpn_range: self.vpn_range,
+            data_frame,
+            map_type: self.map_type,
+            map_permission: self.map_permission,
+            cow: true,
+            referenced: BTreeMap::new(),
+            populated: self.populated.clone(),
+        })
+    }
+
+    /// 处理写时复制缺页：如果该帧仍被其他地址空间共享（强引用计数 > 1），
+    /// 分配一份新帧并拷贝内容；否则说明自己已经是唯一持有者，原地恢复 `W` 位即可。
+    ///
+    /// Same "no caller yet" caveat as `MapArea::handle_page_fault`: nothing
+    /// in this tree decodes a `StorePageFault` on a COW page and calls this.
+    pub fn handle_cow_fault(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) -> MemoryResult<()> {
+        if !self.vpn_range.is_contains(&vpn) {
+            return Err(AreaError::AreaRangeNotInclude.into());
+        }
+        if !self.cow {
+            // 既不缺页也不是共享帧，说明调用方把一次真正的写保护违规误判成了
+            // COW 缺页；交给独立的 `PageError` 变体，让 trap 分发处能与正常的
+            // 延迟拷贝区分开。
+            return Err(PageError::NotCowPage.into());
+        }
+        let pte_flags = PTEFlags::from_bits(self.map_permission.bits).unwrap();
+        let frame = self.data_frame.get(&vpn).ok_or(AreaError::AreaRangeNotInclude)?.clone();
+        // 单页缺页是最常见的路径，这里只记一个地址，`Drop` 时就是一次单地址刷新
+        let mut flusher = Flusher::new();
+        if Arc::strong_count(&frame) > 1 {
+            let new_frame = frame_alloc().ok_or(MemoryError::MemoryNotEnough)?;
+            new_frame
+                .ppn
+                .get_bytes_array()
+                .copy_from_slice(frame.ppn.get_bytes_array());
+            let new_ppn = new_frame.ppn;
+            self.data_frame.insert(vpn, Arc::new(new_frame));
+            page_table.unmap(vpn)?;
+            page_table.map(vpn, new_ppn, pte_flags)?;
+        } else {
+            page_table.unmap(vpn)?;
+            page_table.map(vpn, frame.ppn, pte_flags)?;
+        }
+        flusher.record(vpn);
+        Ok(())
+    }
+
+    /// 修改本区域的映射权限（`sys_mprotect`）。由于没有直接改写 PTE 标志位的
+    /// 原语，对已经建立映射的页采取 unmap 再 map 的方式刷新标志位；尚未建立
+    /// 映射的 `Framed` 页仅需更新 `map_permission`，等到缺页时自然按新权限
+    /// 建立映射。
+    ///
+    /// 如果本区域仍处于 COW 共享状态（`self.cow`）且 `new_perm` 要求 `W`，
+    /// 不能直接把 `W` 位写回共享帧的 PTE——否则子进程单靠 `mprotect` 就能
+    /// 绕开 COW 缺页，直接拿到对父进程仍持有同一份帧的写权限。这里对每个
+    /// 仍被共享（`Arc::strong_count(frame) > 1`）的帧先做一次与
+    /// [`Self::handle_cow_fault`] 相同的复制，解除共享后再授予 `W`；已经是
+    /// 唯一持有者的帧直接恢复 `W` 即可。
+    pub fn set_permission(&mut self, page_table: &mut PageTable, new_perm: MapPermission) -> MemoryResult<()> {
+        self.map_permission = new_perm;
+        let pte_flags = PTEFlags::from_bits(new_perm.bits).unwrap();
+        let wants_write = new_perm.contains(MapPermission::W);
+        let mut flusher = Flusher::new();
+        match self.map_type {
+            MapType::Identical => {
+                for vpn in self.vpn_range {
+                    let ppn = PhysPageNum(vpn.0);
+                    page_table.unmap(vpn)?;
+                    page_table.map(vpn, ppn, pte_flags)?;
+                    flusher.record(vpn);
+                }
+            }
+            MapType::Framed => {
+                let vpns: Vec<VirtPageNum> = self.data_frame.keys().copied().collect();
+                for vpn in vpns {
+                    let ppn = if self.cow && wants_write {
+                        self.copy_cow_frame_if_shared(vpn)?
+                    } else {
+                        self.data_frame.get(&vpn).ok_or(AreaError::AreaRangeNotInclude)?.ppn
+                    };
+                    page_table.unmap(vpn)?;
+                    page_table.map(vpn, ppn, pte_flags)?;
+                    flusher.record(vpn);
+                }
+            }
+            MapType::HugeFramed => {
+                let vpns: Vec<VirtPageNum> = self.data_frame.keys().copied().collect();
+                for vpn in vpns {
+                    let ppn = if self.cow && wants_write {
+                        self.copy_cow_frame_if_shared(vpn)?
+                    } else {
+                        self.data_frame.get(&vpn).ok_or(AreaError::AreaRangeNotInclude)?.ppn
+                    };
+                    page_table.unmap_huge(vpn)?;
+                    page_table.map_huge(vpn, ppn, pte_flags)?;
+                    flusher.record(vpn);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 如果 `vpn` 对应的帧仍被其他地址空间共享（强引用计数 > 1），分配一份
+    /// 新帧拷贝内容并替换 `self.data_frame` 里的条目，解除共享；否则直接
+    /// 返回现有物理页号。抽出来供 [`Self::set_permission`] 复用
+    /// [`Self::handle_cow_fault`] 里“仍共享就先拷贝”的判断。
+    fn copy_cow_frame_if_shared(&mut self, vpn: VirtPageNum) -> MemoryResult<PhysPageNum> {
+        let frame = self.data_frame.get(&vpn).ok_or(AreaError::AreaRangeNotInclude)?.clone();
+        if Arc::strong_count(&frame) > 1 {
+            let new_frame = frame_alloc().ok_or(MemoryError::MemoryNotEnough)?;
+            new_frame
+                .ppn
+                .get_bytes_array()
+                .copy_from_slice(frame.ppn.get_bytes_array());
+            let new_ppn = new_frame.ppn;
+            self.data_frame.insert(vpn, Arc::new(new_frame));
+            Ok(new_ppn)
+        } else {
+            Ok(frame.ppn)
+        }
+    }
 }
 
 /// 内存映射类型
@@ -209,6 +789,9 @@ pub enum MapType {
     Identical,
     /// 帧映射
     Framed,
+    /// 2MiB 巨页映射：用一个 L1 叶子 PTE 覆盖 512 个连续物理帧，省去一级
+    /// 页表遍历，适合大块匿名内存（大堆/大栈）
+    HugeFramed,
 }
 
 bitflags! {
@@ -224,3 +807,58 @@ bitflags! {
         const U = 1 << 4;
     }
 }
+
+bitflags! {
+    /// `mmap`/`mprotect` 的保护标志（对应 Linux 的 `PROT_*`），由
+    /// [`ProtFlags::to_map_permission`] 翻译为内部的 [`MapPermission`]。
+    pub struct ProtFlags: u32 {
+        /// 不可访问
+        const PROT_NONE = 0;
+        /// 可读
+        const PROT_READ = 1 << 0;
+        /// 可写
+        const PROT_WRITE = 1 << 1;
+        /// 可执行
+        const PROT_EXEC = 1 << 2;
+    }
+}
+
+impl ProtFlags {
+    /// 转换为内部的 [`MapPermission`]，总是附带 `U`（用户态可访问）。
+    pub fn to_map_permission(self) -> MapPermission {
+        let mut perm = MapPermission::U;
+        if self.contains(Self::PROT_READ) {
+            perm |= MapPermission::R;
+        }
+        if self.contains(Self::PROT_WRITE) {
+            perm |= MapPermission::W;
+        }
+        if self.contains(Self::PROT_EXEC) {
+            perm |= MapPermission::X;
+        }
+        perm
+    }
+}
+
+bitflags! {
+    /// `mmap` 的映射标志（对应 Linux 的 `MAP_*`）。
+    pub struct MapFlags: u32 {
+        /// 与其他映射/进程共享该区域（本内核暂不支持真正的跨地址空间共享,
+        /// 保留标志位供上层语义使用）
+        const MAP_SHARED = 1 << 0;
+        /// 写时复制的私有映射
+        const MAP_PRIVATE = 1 << 1;
+        /// 必须精确映射到请求的地址，与现有映射重叠时失败，而不是由内核
+        /// 另择地址
+        const MAP_FIXED = 1 << 4;
+        /// 不关联到任何文件，由匿名帧支持（隐含 `MapType::Framed`）
+        const MAP_ANONYMOUS = 1 << 5;
+        /// 不为该映射预留存储空间（本内核没有 swap，保留标志位）
+        const MAP_NORESERVE = 1 << 14;
+        /// 立即建立全部映射，而不是遇到缺页时再按需分配
+        const MAP_POPULATE = 1 << 15;
+        /// 用 2MiB 大页（[`MapType::HugeFramed`]）而不是逐 4KiB 页建立映射；
+        /// `addr`/`len` 必须按 `HUGE_PAGE_SIZE` 对齐
+        const MAP_HUGETLB = 1 << 16;
+    }
+}
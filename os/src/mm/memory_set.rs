@@ -1,12 +1,12 @@
 //! Implementation of [`MapArea`] and [`MemorySet`].
 
-use super::{MapArea, MapPermission, MapType};
+use super::{MapArea, MapFlags, MapPermission, MapType, ProtFlags};
 use super::{PTEFlags, PageTable, PageTableEntry};
 use super::{PhysAddr, VirtAddr, VirtPageNum};
 use super::VPNRange;
-use super::error::{AreaError, MemoryResult};
+use super::error::{AreaError, MemoryError, MemoryResult, PagePermissionError};
 use crate::config::{
-    KERNEL_STACK_SIZE, MEMORY_END, PAGE_SIZE, TRAMPOLINE, TRAP_CONTEXT_BASE, USER_STACK_SIZE,
+    MEMORY_END, PAGE_SIZE, TRAMPOLINE, TRAP_CONTEXT_BASE, USER_STACK_SIZE,
 };
 use crate::sync::UPSafeCell;
 use alloc::sync::Arc;
@@ -60,11 +60,24 @@ impl MemorySet {
         permission: MapPermission,
     ) -> MemoryResult<()> {
         self.push(
-            MapArea::new(start_va, end_va, MapType::Framed, permission),
+            MapArea::new(start_va, end_va, MapType::Framed, permission)?,
             None,
         )
     }
-    /// 延迟插入
+    /// 延迟插入：只登记区域，`Framed` 页既不建立 PTE 也不预先分配物理帧（见
+    /// [`Self::push_lazy`]，`from_elf` 的用户栈也直接走这条惰性路径）。
+    ///
+    /// STATUS: only half-lazy in practice. The page *stays* unbacked until
+    /// something calls [`Self::handle_page_fault`], and that call has to
+    /// come from a trap dispatcher decoding a real `LoadPageFault`/
+    /// `StorePageFault`/`InstructionPageFault` — this repo slice has no
+    /// `trap` module (`os/src/trap/` doesn't exist here, only
+    /// `crate::trap::{TrapContext, trap_handler}` referenced from outside
+    /// `mm`), so nothing ever calls it. A first touch of one of these pages
+    /// today hits whatever happens on an unhandled exception, not a
+    /// demand-paged allocation. The lazy bookkeeping is real and ready for
+    /// that dispatcher to call into; wiring it up is blocked on a `trap/`
+    /// module this slice doesn't have, not unimplemented on the `mm` side.
     pub fn insert_framed_area_lazy(
         &mut self,
         start_va: VirtAddr,
@@ -72,19 +85,48 @@ impl MemorySet {
         permission: MapPermission,
     ) -> MemoryResult<()> {
         self.push_lazy(
-            MapArea::new(start_va, end_va, MapType::Framed, permission),
+            MapArea::new(start_va, end_va, MapType::Framed, permission)?,
             None,
         )
     }
     fn push(&mut self, mut map_area: MapArea, data: Option<&[u8]>) -> MemoryResult<()> {
         map_area.map(&mut self.page_table)?;
-        map_area.check_all_page(&mut self.page_table)?; // force allocation
+        self.check_all_page_with_reclaim(&mut map_area)?; // force allocation
         if let Some(data) = data {
             map_area.copy_data(&mut self.page_table, data)?;
         }
         self.areas.push(map_area);
         Ok(())
     }
+    /// 数量上限不大，只是避免一次回收遍历扫太多区域；`reclaim_clean_pages`
+    /// 拿不到这么多干净页也没关系，重试会按实际腾出的帧尽量推进。
+    const RECLAIM_BATCH: usize = 64;
+    /// `MapArea::check_all_page` 失败于 `MemoryError::MemoryNotEnough` 时，
+    /// 先用 [`Self::reclaim_clean_pages`] 腾出别的区域里的干净页再重试一次；
+    /// 其余错误或重试后仍然失败，原样传播。
+    fn check_all_page_with_reclaim(&mut self, area: &mut MapArea) -> MemoryResult<()> {
+        match area.check_all_page(&mut self.page_table) {
+            Err(MemoryError::MemoryNotEnough) => {
+                self.reclaim_clean_pages(Self::RECLAIM_BATCH)?;
+                area.check_all_page(&mut self.page_table)
+            }
+            other => other,
+        }
+    }
+    /// 遍历所有已映射区域，调用 [`MapArea::reclaim_clean`] 回收至多 `want`
+    /// 个干净页，在凑够之前提前返回。这就是 mm 模块文档里说的
+    /// "clock/second-chance eviction"：每个区域各自维护的 `referenced` 位第一轮
+    /// 只是清空，真正的回收要等到同一个页第二次被扫到。
+    fn reclaim_clean_pages(&mut self, want: usize) -> MemoryResult<usize> {
+        let mut reclaimed = 0;
+        for area in self.areas.iter_mut() {
+            if reclaimed >= want {
+                break;
+            }
+            reclaimed += area.reclaim_clean(&mut self.page_table, want - reclaimed)?;
+        }
+        Ok(reclaimed)
+    }
     fn push_lazy(&mut self, mut map_area: MapArea, data: Option<&[u8]>) -> MemoryResult<()> {
         map_area.map(&mut self.page_table)?;
         if let Some(data) = data {
@@ -123,7 +165,7 @@ impl MemorySet {
                 (etext as usize).into(),
                 MapType::Identical,
                 MapPermission::R | MapPermission::X,
-            ),
+            ).unwrap(),
             None,
         ).unwrap();
         info!("Map .rodata section");
@@ -133,7 +175,7 @@ impl MemorySet {
                 (erodata as usize).into(),
                 MapType::Identical,
                 MapPermission::R,
-            ),
+            ).unwrap(),
             None,
         ).unwrap();
         info!("Map .data section");
@@ -143,7 +185,7 @@ impl MemorySet {
                 (edata as usize).into(),
                 MapType::Identical,
                 MapPermission::R | MapPermission::W,
-            ),
+            ).unwrap(),
             None,
         ).unwrap();
         info!("Map .bss section");
@@ -153,7 +195,7 @@ impl MemorySet {
                 (ebss as usize).into(),
                 MapType::Identical,
                 MapPermission::R | MapPermission::W,
-            ),
+            ).unwrap(),
             None,
         ).unwrap();
         info!("Map physical memory");
@@ -163,7 +205,7 @@ impl MemorySet {
                 MEMORY_END.into(),
                 MapType::Identical,
                 MapPermission::R | MapPermission::W,
-            ),
+            ).unwrap(),
             None,
         ).unwrap();
         memory_set
@@ -197,7 +239,7 @@ impl MemorySet {
                 if ph_flags.is_execute() {
                     map_perm |= MapPermission::X;
                 }
-                let map_area = MapArea::new(start_va, end_va, MapType::Framed, map_perm);
+                let map_area = MapArea::new(start_va, end_va, MapType::Framed, map_perm)?;
                 max_end_vpn = map_area.get_vpn_range().get_end();
                 memory_set.push(
                     map_area,
@@ -217,7 +259,7 @@ impl MemorySet {
                 user_stack_top.into(),
                 MapType::Framed,
                 MapPermission::R | MapPermission::W | MapPermission::U,
-            ),
+            )?,
             None,
         )?;
         // used in sbrk
@@ -227,7 +269,7 @@ impl MemorySet {
                 user_stack_top.into(),
                 MapType::Framed,
                 MapPermission::R | MapPermission::W | MapPermission::U,
-            ),
+            )?,
             None,
         )?;
         // map TrapContext
@@ -238,7 +280,7 @@ impl MemorySet {
                 TRAMPOLINE.into(),
                 MapType::Framed,
                 MapPermission::R | MapPermission::W,
-            ),
+            )?,
             None,
         )?;
         Ok((
@@ -255,12 +297,65 @@ impl MemorySet {
             asm!("sfence.vma");
         }
     }
+    /// 处理来自 trap 分发器的缺页异常（`LoadPageFault`/`StorePageFault`/
+    /// `InstructionPageFault`）。先在 `areas` 中定位故障地址所属的
+    /// `MapArea`：找不到视为真正的段错误（`AreaError::AreaRangeNotInclude`），
+    /// 调用方应当杀掉对应任务；找到了但访问类型与 `MapPermission` 不符，返回
+    /// `PagePermissionError`，同样需要杀掉任务而不是重试指令。其余情况要么是
+    /// 写时复制的延迟拷贝（交给 `MapArea::handle_cow_fault`），要么是
+    /// `push_lazy` 留下的尚未分配物理帧的惰性页，按需分配后即可让陷入现场
+    /// 重新执行故障指令。
+    ///
+    /// No caller yet: this tree's `trap` module (the dispatcher that would
+    /// decode `scause` and route a real `LoadPageFault`/`StorePageFault`
+    /// here) isn't present in this repo slice, so lazy `Framed` mappings and
+    /// COW-fork both still fall through to whatever the old unconditional
+    /// path does on a real fault. Wiring this in is out of scope here and
+    /// belongs in its own request once `trap/` exists to edit.
+    pub fn handle_page_fault(&mut self, va: VirtAddr, access: PageFaultAccess) -> MemoryResult<()> {
+        let vpn = va.floor();
+        let area = self
+            .areas
+            .iter_mut()
+            .find(|area| area.get_vpn_range().is_contains(&vpn))
+            .ok_or(AreaError::AreaRangeNotInclude)?;
+        let perm = area.get_map_permission();
+        match access {
+            PageFaultAccess::Load if !perm.contains(MapPermission::R) => {
+                return Err(PagePermissionError::Unreadable.into());
+            }
+            PageFaultAccess::Instruction if !perm.contains(MapPermission::X) => {
+                return Err(PagePermissionError::Unexecutable.into());
+            }
+            PageFaultAccess::Store if !perm.contains(MapPermission::W) => {
+                return Err(PagePermissionError::Unwritable.into());
+            }
+            _ => {}
+        }
+        if access == PageFaultAccess::Store && area.is_cow() {
+            return area.handle_cow_fault(&mut self.page_table, vpn);
+        }
+        match area.handle_page_fault(&mut self.page_table, vpn) {
+            Err(MemoryError::MemoryNotEnough) => {}
+            other => return other,
+        }
+        // `area`'s borrow of `self.areas` ends above; reclaim clean pages
+        // from other areas and retry the allocation once before giving up,
+        // same as `Self::check_all_page_with_reclaim`.
+        self.reclaim_clean_pages(Self::RECLAIM_BATCH)?;
+        let area = self
+            .areas
+            .iter_mut()
+            .find(|area| area.get_vpn_range().is_contains(&vpn))
+            .ok_or(AreaError::AreaRangeNotInclude)?;
+        area.handle_page_fault(&mut self.page_table, vpn)
+    }
     /// 将虚拟页号映射到页表
     pub fn transform(&mut self, vpn: VirtPageNum) -> MemoryResult<PageTableEntry> {
         if let Some(area) = self.areas.iter_mut().find(|x|x.get_vpn_range().is_contains(&vpn)) {
             area.check_range(&mut self.page_table, VPNRange::new_by_len(vpn, 1))?;
         } else {
-            return Err(AreaError::NotInclude.into())
+            return Err(AreaError::AreaRangeNotInclude.into())
         };
         self.page_table.translate(vpn)
     }
@@ -274,7 +369,7 @@ impl MemorySet {
         {
             area.narrow(&mut self.page_table, new_end.ceil())
         } else {
-            Err(AreaError::NotMatch.into())
+            Err(AreaError::NoMatchingArea.into())
         }
     }
 
@@ -288,7 +383,7 @@ impl MemorySet {
         {
             area.expand(&mut self.page_table, new_end.ceil())
         } else {
-            Err(AreaError::NotMatch.into())
+            Err(AreaError::NoMatchingArea.into())
         }
     }
 
@@ -315,38 +410,178 @@ impl MemorySet {
         return false;
     }
 
-    /// 尝试映射虚拟内存
-    pub fn map_memory(
+    /// 尝试取消映射除关键内存之外的虚拟内存
+    pub fn unmap_memory(
         &mut self,
         start_va: VirtAddr,
         end_va: VirtAddr,
-        permission: MapPermission,
     ) -> MemoryResult<()>  {
-        let area = MapArea::new(start_va, end_va, MapType::Framed, permission);
-        if area.get_vpn_range().into_iter().any(|x|self.is_critical(x)) {
-            return Err(AreaError::CriticalArea.into());
+        let target_range = VPNRange::new(start_va.floor(), end_va.ceil());
+        if target_range.into_iter().any(|x|self.is_critical(x)) {
+            return Err(AreaError::AreaCritical.into());
+        }
+        if self.is_unmapped(target_range) {
+            return Err(AreaError::AreaHasUnmappedPortion.into());
+        }
+        let areas = core::mem::take(&mut self.areas);
+        for area in areas.into_iter() {
+            let (l, _, rem) = area.get_vpn_range().exclude(&target_range);
+            if rem.is_empty() {
+                self.areas.push(area);
+                continue;
+            }
+            let (larea, rarea) = area.split(l.get_end());
+            let (mut marea, rarea) = rarea.split(rem.get_end());
+            if !larea.get_vpn_range().is_empty() {
+                self.areas.push(larea);
+            }
+            if !rarea.get_vpn_range().is_empty() {
+                self.areas.push(rarea);
+            }
+            marea.unmap(&mut self.page_table)?;
+            drop(marea);
+        }
+        Ok(())
+    }
+
+    /// 按 Linux 风格的 `ProtFlags`/`MapFlags` 建立一个新的用户映射。
+    /// `MAP_FIXED` 要求精确映射到 `addr`，与现有区域重叠时返回
+    /// `AreaError::AreaHasMappedPortion`；否则从 `addr` 起挑选一段空闲区间。
+    /// `MAP_ANONYMOUS` 隐含 `MapType::Framed`（本内核还没有文件映射，所有
+    /// `mmap` 目前都是匿名的）。`MAP_POPULATE` 立即建立全部映射，否则保持
+    /// 惰性，交给 [`Self::handle_page_fault`] 按需分配。`MAP_HUGETLB` 改用
+    /// `MapType::HugeFramed`（2MiB 大页）而不是逐 4KiB 页映射，`addr`/`len`
+    /// 必须按 2MiB 对齐，否则 `MapArea::new` 会返回错误。
+    ///
+    /// With `MAP_POPULATE`, an allocation failure (`MemoryError::MemoryNotEnough`
+    /// from `check_all_page`) doesn't fail outright: [`Self::check_all_page_with_reclaim`]
+    /// first asks every other `Framed` area in this `MemorySet` to give up its
+    /// clean pages (see [`MapArea::reclaim_clean`]) and retries once before
+    /// giving up. Without `MAP_POPULATE`, the mapping stays lazy and the same
+    /// reclaim-then-retry happens later inside [`Self::handle_page_fault`]'s
+    /// per-page allocation.
+    pub fn mmap(
+        &mut self,
+        addr: VirtAddr,
+        len: usize,
+        prot: ProtFlags,
+        flags: MapFlags,
+    ) -> MemoryResult<VirtAddr> {
+        let permission = prot.to_map_permission();
+        let map_type = if flags.contains(MapFlags::MAP_HUGETLB) {
+            MapType::HugeFramed
+        } else {
+            MapType::Framed
+        };
+        let start_va = if flags.contains(MapFlags::MAP_FIXED) {
+            addr
+        } else {
+            self.find_free_area(addr, len)?
+        };
+        let end_va: VirtAddr = (start_va.0 + len).into();
+        let mut area = MapArea::new(start_va, end_va, map_type, permission)?;
+        if area.get_vpn_range().into_iter().any(|x| self.is_critical(x)) {
+            return Err(AreaError::AreaCritical.into());
         }
         if self.is_mapped(area.get_vpn_range()) {
-            return Err(AreaError::ContainMapped.into());
+            return Err(AreaError::AreaHasMappedPortion.into());
         }
-        self.push_lazy(
-            area,
-            None,
-        )
+        if flags.contains(MapFlags::MAP_POPULATE) {
+            self.check_all_page_with_reclaim(&mut area)?;
+        }
+        self.areas.push(area);
+        Ok(start_va)
     }
 
-    /// 尝试取消映射除关键内存之外的虚拟内存
-    pub fn unmap_memory(
+    /// 从 `hint` 起寻找一段 `len` 字节的空闲虚拟地址区间，供未携带
+    /// `MAP_FIXED` 的 [`Self::mmap`] 使用。
+    fn find_free_area(&self, hint: VirtAddr, len: usize) -> MemoryResult<VirtAddr> {
+        let page_count = (len + PAGE_SIZE - 1) / PAGE_SIZE;
+        let mut start_vpn = hint.floor();
+        loop {
+            let candidate = VPNRange::new_by_len(start_vpn, page_count);
+            if candidate.into_iter().any(|x| self.is_critical(x)) {
+                return Err(AreaError::AreaCritical.into());
+            }
+            if !self.is_mapped(candidate) {
+                return Ok(start_vpn.into());
+            }
+            start_vpn = candidate.get_end();
+        }
+    }
+
+    /// 调整一段已有映射的大小, 必要时移动它（`mremap`）。按 `old_start_va`
+    /// 定位现有区域, 校验其长度与 `old_len` 一致；若新长度能在原地容纳
+    /// （不与其他区域重叠）就地 `narrow`/`expand`，否则在 `allow_move` 为真
+    /// 时用 `find_free_area` 挑一段新区间搬过去, 为假则报告
+    /// `AreaError::AreaHasMappedPortion`。成功时返回新映射的起始地址。
+    ///
+    /// 与 `unmap_memory`/`mmap`/`protect_memory` 一样，原区间和最终落点都要
+    /// 经过 `is_critical` 校验：`TRAMPOLINE`/`TRAP_CONTEXT_BASE` 所在的区域
+    /// 不允许被 `mremap` 挪动或伸缩，否则内核陷入上下文页可能被用户态重新
+    /// 映射到别处。
+    pub fn mremap(
+        &mut self,
+        old_start_va: VirtAddr,
+        old_len: usize,
+        new_len: usize,
+        allow_move: bool,
+    ) -> MemoryResult<VirtAddr> {
+        let old_start_vpn = old_start_va.floor();
+        let index = self
+            .areas
+            .iter()
+            .position(|a| a.get_vpn_range().get_start() == old_start_vpn)
+            .ok_or(AreaError::NoMatchingArea)?;
+        let old_page_count = (old_len + PAGE_SIZE - 1) / PAGE_SIZE;
+        if self.areas[index].get_vpn_range().into_iter().count() != old_page_count {
+            return Err(AreaError::AreaRangeNotInclude.into());
+        }
+        if self.areas[index].get_vpn_range().into_iter().any(|x| self.is_critical(x)) {
+            return Err(AreaError::AreaCritical.into());
+        }
+        let new_page_count = (new_len + PAGE_SIZE - 1) / PAGE_SIZE;
+        let new_end_vpn = VPNRange::new_by_len(old_start_vpn, new_page_count).get_end();
+        let old_end_vpn = self.areas[index].get_vpn_range().get_end();
+        let grows_in_place = new_end_vpn.0 <= old_end_vpn.0 || {
+            let grown_part = VPNRange::new(old_end_vpn, new_end_vpn);
+            !self
+                .areas
+                .iter()
+                .enumerate()
+                .any(|(i, a)| i != index && a.get_vpn_range().intersects(&grown_part))
+        };
+        let target_start_vpn = if grows_in_place {
+            old_start_vpn
+        } else if allow_move {
+            self.find_free_area(old_start_va, new_len)?.floor()
+        } else {
+            return Err(AreaError::AreaHasMappedPortion.into());
+        };
+        if VPNRange::new_by_len(target_start_vpn, new_page_count).into_iter().any(|x| self.is_critical(x)) {
+            return Err(AreaError::AreaCritical.into());
+        }
+        let moving = target_start_vpn != old_start_vpn;
+        self.areas[index].remap(&mut self.page_table, target_start_vpn, new_page_count, moving)?;
+        Ok(target_start_vpn.into())
+    }
+
+    /// 修改 `[start_va, end_va)` 的内存保护权限（`sys_mprotect`），要求该区间
+    /// 被已映射的区域完整覆盖且不与关键区域重叠；与 `unmap_memory` 一样，
+    /// 跨越区域边界的部分通过 `split` 切出来单独处理，互不影响的部分原样
+    /// 保留。
+    pub fn protect_memory(
         &mut self,
         start_va: VirtAddr,
         end_va: VirtAddr,
-    ) -> MemoryResult<()>  {
+        permission: MapPermission,
+    ) -> MemoryResult<()> {
         let target_range = VPNRange::new(start_va.floor(), end_va.ceil());
-        if target_range.into_iter().any(|x|self.is_critical(x)) {
-            return Err(AreaError::CriticalArea.into());
+        if target_range.into_iter().any(|x| self.is_critical(x)) {
+            return Err(AreaError::AreaCritical.into());
         }
         if self.is_unmapped(target_range) {
-            return Err(AreaError::ContainUnmapped.into());
+            return Err(AreaError::AreaHasUnmappedPortion.into());
         }
         let areas = core::mem::take(&mut self.areas);
         for area in areas.into_iter() {
@@ -363,18 +598,67 @@ impl MemorySet {
             if !rarea.get_vpn_range().is_empty() {
                 self.areas.push(rarea);
             }
-            marea.unmap(&mut self.page_table)?;
-            drop(marea);
+            marea.set_permission(&mut self.page_table, permission)?;
+            self.areas.push(marea);
         }
         Ok(())
     }
+
+    /// 基于父进程地址空间创建一份写时复制(COW)的子地址空间, 供 `fork` 使用。
+    ///
+    /// `TRAMPOLINE`/`TrapContext` 等关键页不参与 COW, 这里复用 `is_critical`
+    /// 跳过它们：`TRAMPOLINE` 由 `map_trampoline` 重新映射，包含关键页的区域
+    /// （`TrapContext`）在子进程中分配新帧并立即整页拷贝父进程的内容。其余
+    /// `Framed` 区域与父进程共享同一物理帧, 并在双方页表中清除 `W` 位, 真正
+    /// 的复制延迟到 `MapArea::handle_cow_fault` 里的写缺页处理中完成。
+    pub fn from_existed_user(parent: &mut MemorySet) -> MemoryResult<Self> {
+        let mut child = Self::new_bare()?;
+        child.map_trampoline()?;
+        for area in parent.areas.iter_mut() {
+            if area.get_vpn_range().into_iter().any(|vpn| parent_is_critical(vpn)) {
+                // 关键区域（目前只有 TRAMPOLINE/TrapContext, 两者均不在 areas 中
+                // 记录，这里仅作为未来扩展关键区域时的保险丝）仍然眼前复制。
+                let mut new_area = MapArea::new(
+                    area.get_vpn_range().get_start().into(),
+                    area.get_vpn_range().get_end().into(),
+                    MapType::Framed,
+                    area.get_map_permission(),
+                )?;
+                new_area.map(&mut child.page_table)?;
+                new_area.check_all_page(&mut child.page_table)?;
+                for vpn in area.get_vpn_range() {
+                    let src = parent.page_table.translate(vpn)?.ppn();
+                    let dst = child.page_table.translate(vpn)?.ppn();
+                    dst.get_bytes_array().copy_from_slice(src.get_bytes_array());
+                }
+                child.areas.push(new_area);
+                continue;
+            }
+            let new_area = area.clone_cow(&mut parent.page_table, &mut child.page_table)?;
+            child.areas.push(new_area);
+        }
+        Ok(child)
+    }
 }
 
-/// Return (bottom, top) of a kernel stack in kernel space.
-pub fn kernel_stack_position(app_id: usize) -> (usize, usize) {
-    let top = TRAMPOLINE - app_id * (KERNEL_STACK_SIZE + PAGE_SIZE);
-    let bottom = top - KERNEL_STACK_SIZE;
-    (bottom, top)
+/// `MemorySet::from_existed_user` 的关键区域判定复用 `MemorySet::is_critical`
+/// 的逻辑，但后者需要 `&self`，克隆时我们同时持有父子双方的可变引用，因此这里
+/// 提供一个自由函数版本。
+fn parent_is_critical(vpn: VirtPageNum) -> bool {
+    vpn == VirtPageNum::from(VirtAddr::from(TRAMPOLINE))
+        || vpn == VirtPageNum::from(VirtAddr::from(TRAP_CONTEXT_BASE))
+}
+
+/// 触发缺页异常的访问类型，由 trap 分发器根据 `scause` 区分后传入
+/// [`MemorySet::handle_page_fault`]。
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum PageFaultAccess {
+    /// `Exception::LoadPageFault`
+    Load,
+    /// `Exception::StorePageFault`
+    Store,
+    /// `Exception::InstructionPageFault`
+    Instruction,
 }
 
 /// remap test in kernel space
@@ -400,4 +684,93 @@ pub fn remap_test() {
         .unwrap()
         .executable(),);
     println!("remap_test passed!");
+}
+
+/// Sanity check for the dirty/`populated` gating on [`MapArea::reclaim_clean`]
+/// (see the "Out-of-memory handling" note on the `mm` module doc): a page
+/// that's never been written should be reclaimed once the software
+/// "referenced" bit has given it one pass to be re-touched, while a page
+/// [`MapArea::copy_data`] has written real bytes into must stay mapped no
+/// matter how many reclaim passes run, since there's no swap to restore it
+/// from afterwards.
+#[allow(unused)]
+pub fn reclaim_test() {
+    let mut ms = MemorySet::new_bare().unwrap();
+    let perm = MapPermission::R | MapPermission::W | MapPermission::U;
+
+    let clean_start: VirtAddr = 0x1_0000_0000.into();
+    let clean_end: VirtAddr = (0x1_0000_0000 + PAGE_SIZE).into();
+    ms.insert_framed_area(clean_start, clean_end, perm).unwrap();
+
+    let written_start: VirtAddr = (0x1_0000_0000 + 2 * PAGE_SIZE).into();
+    let written_end: VirtAddr = (0x1_0000_0000 + 3 * PAGE_SIZE).into();
+    let written_area = MapArea::new(written_start, written_end, MapType::Framed, perm).unwrap();
+    ms.push(written_area, Some(&[0x42u8; PAGE_SIZE])).unwrap();
+
+    let clean_vpn = clean_start.floor();
+    let written_vpn = written_start.floor();
+    assert!(ms.page_table.translate(clean_vpn).is_ok());
+    assert!(ms.page_table.translate(written_vpn).is_ok());
+
+    // First pass only clears the "referenced" second chance; nothing is
+    // evicted yet.
+    ms.reclaim_clean_pages(MemorySet::RECLAIM_BATCH).unwrap();
+    assert!(ms.page_table.translate(clean_vpn).is_ok());
+    assert!(ms.page_table.translate(written_vpn).is_ok());
+
+    // Second pass: the never-written page is reclaimed, the copy_data-written
+    // page is protected by `MapArea::populated` and stays mapped.
+    ms.reclaim_clean_pages(MemorySet::RECLAIM_BATCH).unwrap();
+    assert!(ms.page_table.translate(clean_vpn).is_err());
+    assert!(ms.page_table.translate(written_vpn).is_ok());
+
+    println!("reclaim_test passed!");
+}
+
+/// Sanity check for the `mmap`/`unmap_memory`/`protect_memory`/`mremap`
+/// family (`sys_mmap`/`sys_munmap`/`sys_mprotect`/`sys_mremap`): map two
+/// pages, drop one with `unmap_memory`, flip the survivor read-only with
+/// `protect_memory`, then grow it back to two pages with `mremap` and check
+/// each step actually changed what the area/page table report.
+#[allow(unused)]
+pub fn mmap_test() {
+    let mut ms = MemorySet::new_bare().unwrap();
+    let base: VirtAddr = 0x2_0000_0000.into();
+    let len = 2 * PAGE_SIZE;
+    let start = ms
+        .mmap(
+            base,
+            len,
+            ProtFlags::PROT_READ | ProtFlags::PROT_WRITE,
+            MapFlags::MAP_FIXED | MapFlags::MAP_ANONYMOUS | MapFlags::MAP_PRIVATE | MapFlags::MAP_POPULATE,
+        )
+        .unwrap();
+    assert_eq!(start, base);
+    let first_vpn = base.floor();
+    let second_vpn = VirtAddr::from(base.0 + PAGE_SIZE).floor();
+    assert!(ms.page_table.translate(first_vpn).is_ok());
+    assert!(ms.page_table.translate(second_vpn).is_ok());
+
+    // munmap the second page only; the first must stay untouched.
+    ms.unmap_memory(VirtAddr::from(base.0 + PAGE_SIZE), VirtAddr::from(base.0 + len))
+        .unwrap();
+    assert!(ms.page_table.translate(first_vpn).is_ok());
+    assert!(ms.page_table.translate(second_vpn).is_err());
+
+    // mprotect the remaining page to read-only.
+    ms.protect_memory(base, VirtAddr::from(base.0 + PAGE_SIZE), MapPermission::R | MapPermission::U)
+        .unwrap();
+    assert!(!ms.page_table.translate(first_vpn).unwrap().writable());
+
+    // mremap: grow the one remaining page back to two pages in place. The
+    // grown half stays lazily unbacked (`MapArea::map_one` installs nothing
+    // for `Framed`; see the STATUS note on `MapArea::handle_page_fault`), but the area's range must cover it, so a `protect_memory`
+    // spanning both pages should now succeed instead of reporting
+    // `AreaHasUnmappedPortion`.
+    let new_start = ms.mremap(base, PAGE_SIZE, 2 * PAGE_SIZE, true).unwrap();
+    assert_eq!(new_start, base);
+    ms.protect_memory(base, VirtAddr::from(base.0 + len), MapPermission::R | MapPermission::W | MapPermission::U)
+        .unwrap();
+
+    println!("mmap_test passed!");
 }
\ No newline at end of file
@@ -5,6 +5,50 @@
 //! map area and memory set, is implemented here.
 //!
 //! Every task or process has a memory_set to control its virtual memory.
+//!
+//! ## Out-of-memory handling (partial)
+//!
+//! An allocation failure in [`MemorySet::mmap`]/`check_all_page`/COW no
+//! longer panics the kernel — it propagates a [`MemoryError`] so only the
+//! offending task is killed. `check_all_page`'s callers (`MemorySet::push`,
+//! `MemorySet::mmap` with `MAP_POPULATE`) and the lazy per-page path in
+//! `MemorySet::handle_page_fault` go one step further: on
+//! `MemoryError::MemoryNotEnough` they call `MemorySet::reclaim_clean_pages`,
+//! which walks every other `Framed` area and evicts pages a software
+//! "referenced" bit has already given one second chance to
+//! (`MapArea::reclaim_clean`), then retry the failed allocation once.
+//!
+//! There is no swap: an evicted frame is freed outright, and
+//! `MapArea::check_page_raw` hands back a fresh **zeroed** frame the next
+//! time that page is touched, instead of restoring what was there. So
+//! `reclaim_clean` only evicts a page once two separate checks agree losing
+//! it is equivalent to it never having held anything: the PTE's hardware `D`
+//! (dirty) bit must be clear (SV39 on the targeted QEMU `virt` machine sets
+//! `A`/`D` automatically on access/store, so this needs no trap-based
+//! tracking), and `MapArea::populated` must have no entry for it (the one
+//! write path `D` can't see: `MapArea::copy_data` loads ELF `.data`/`.text`
+//! by writing through the frame's physical address, bypassing the user PTE
+//! entirely). A page failing either check is left mapped and is reconsidered
+//! on the next reclaim pass rather than evicted.
+//!
+//! The software "referenced" second chance has no access-bit tracking behind
+//! it, though: nothing in this tree's trap handling (or lack thereof, see the
+//! "no caller yet" note on `MemorySet::handle_page_fault`) ever re-sets
+//! `referenced` after a page is faulted in, so the second chance only ever
+//! fires once per page and degrades to reclaiming in allocation order among
+//! the pages that pass the dirty/populated checks. `fork`/`exec`
+//! (`TaskControlBlock::fork`/`exec`) still fail outright on
+//! `MemoryError::MemoryNotEnough` without attempting reclamation first — they
+//! build a whole new `MemorySet` up front rather than allocating one page at
+//! a time, so there's no single failing allocation to retry around.
+//!
+//! [`memory_set::reclaim_test`] and [`memory_set::mmap_test`] are
+//! area-level sanity checks for the reclaim path above and for the
+//! `mmap`/`munmap_memory`/`protect_memory`/`mremap` family, in the same
+//! `#[allow(unused)] pub fn ..._test()` + `assert!` style as the existing
+//! [`memory_set::remap_test`] — this is a `no_std` kernel with no
+//! `#[test]` harness, so these are meant to be called from kernel init
+//! alongside `remap_test`, not run by `cargo test`.
 
 mod address;
 mod error;
@@ -17,10 +61,10 @@ mod page_table;
 pub use address::{PhysAddr, PhysPageNum, VirtAddr, VirtPageNum};
 use address::{StepByOne, VPNRange};
 pub use error::{MemoryResult, MemoryError, AreaError, PageError, PagePermissionError};
-pub use frame_allocator::{frame_alloc, FrameTracker};
-pub use memory_set::{kernel_stack_position, remap_test, MemorySet, KERNEL_SPACE};
-pub use memory_area::{MapArea, MapPermission, MapType};
-pub use page_table::{translated_byte_buffer, PageTableEntry};
+pub use frame_allocator::{frame_alloc, frame_alloc_contiguous, FrameTracker};
+pub use memory_set::{mmap_test, reclaim_test, remap_test, MemorySet, PageFaultAccess, KERNEL_SPACE};
+pub use memory_area::{mmap_min_addr, set_mmap_min_addr, MapArea, MapFlags, MapPermission, MapType, ProtFlags};
+pub use page_table::{translated_byte_buffer, translated_str, PageTableEntry};
 use page_table::{PTEFlags, PageTable};
 
 /// initiate heap allocator, frame allocator and kernel space
@@ -0,0 +1,275 @@
+//! Implementation of [`PageTableEntry`] and [`PageTable`].
+
+use super::{frame_alloc, FrameTracker, MemoryError, MemoryResult, PageError};
+use super::{PhysAddr, PhysPageNum, StepByOne, VirtAddr, VirtPageNum};
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use bitflags::*;
+
+bitflags! {
+    /// page table entry flags
+    pub struct PTEFlags: u8 {
+        /// valid
+        const V = 1 << 0;
+        /// readable
+        const R = 1 << 1;
+        /// writable
+        const W = 1 << 2;
+        /// executable
+        const X = 1 << 3;
+        /// user可访问
+        const U = 1 << 4;
+        /// global
+        const G = 1 << 5;
+        /// accessed
+        const A = 1 << 6;
+        /// dirty
+        const D = 1 << 7;
+    }
+}
+
+#[derive(Copy, Clone)]
+#[repr(C)]
+/// page table entry structure
+pub struct PageTableEntry {
+    /// bits of page table entry
+    pub bits: usize,
+}
+
+impl PageTableEntry {
+    /// Create a new page table entry
+    pub fn new(ppn: PhysPageNum, flags: PTEFlags) -> Self {
+        PageTableEntry {
+            bits: ppn.0 << 10 | flags.bits as usize,
+        }
+    }
+    /// Create an empty page table entry
+    pub fn empty() -> Self {
+        PageTableEntry { bits: 0 }
+    }
+    /// Get the physical page number from the page table entry
+    pub fn ppn(&self) -> PhysPageNum {
+        (self.bits >> 10 & ((1usize << 44) - 1)).into()
+    }
+    /// Get the flags from the page table entry
+    pub fn flags(&self) -> PTEFlags {
+        PTEFlags::from_bits(self.bits as u8).unwrap()
+    }
+    /// Check if the page table entry is valid
+    pub fn is_valid(&self) -> bool {
+        (self.flags() & PTEFlags::V) != PTEFlags::empty()
+    }
+    /// Check if the page table entry is readable
+    pub fn readable(&self) -> bool {
+        (self.flags() & PTEFlags::R) != PTEFlags::empty()
+    }
+    /// Check if the page table entry is writable
+    pub fn writable(&self) -> bool {
+        (self.flags() & PTEFlags::W) != PTEFlags::empty()
+    }
+    /// Check if the page table entry is executable
+    pub fn executable(&self) -> bool {
+        (self.flags() & PTEFlags::X) != PTEFlags::empty()
+    }
+    /// Check if the page table entry's hardware dirty (`D`) bit is set,
+    /// i.e. whether anything has been stored through this mapping since it
+    /// was created. SV39 on the targeted QEMU `virt` machine sets `A`/`D`
+    /// automatically on access/store without trapping, so this is readable
+    /// straight off the PTE with no software dirty-tracking required.
+    pub fn dirty(&self) -> bool {
+        (self.flags() & PTEFlags::D) != PTEFlags::empty()
+    }
+}
+
+/// page table structure
+pub struct PageTable {
+    root_ppn: PhysPageNum,
+    frames: Vec<FrameTracker>,
+}
+
+/// Assume that it won't oom when creating/mapping.
+impl PageTable {
+    /// Create a new page table
+    pub fn new() -> MemoryResult<Self> {
+        let frame = frame_alloc().ok_or(MemoryError::MemoryNotEnough)?;
+        Ok(PageTable {
+            root_ppn: frame.ppn,
+            frames: vec![frame],
+        })
+    }
+    /// Temporarily used to get arguments from user space.
+    pub fn from_token(satp: usize) -> Self {
+        Self {
+            root_ppn: PhysPageNum::from(satp & ((1usize << 44) - 1)),
+            frames: Vec::new(),
+        }
+    }
+    /// 沿 `idxs` 走 `idxs.len()` 级页表，`create` 为真时为缺失的目录页分配新帧；
+    /// 最后一级的 PTE（无论是 4KiB 叶子还是巨页叶子）原样返回，由调用方决定
+    /// 怎么写。目录页（非最后一级）无效且 `create` 为假时返回 `None`。
+    fn walk(&mut self, idxs: &[usize], create: bool) -> Option<&mut PageTableEntry> {
+        let mut ppn = self.root_ppn;
+        let last = idxs.len() - 1;
+        for (i, &idx) in idxs.iter().enumerate() {
+            let pte = &mut ppn.get_pte_array()[idx];
+            if i == last {
+                return Some(pte);
+            }
+            if !pte.is_valid() {
+                if !create {
+                    return None;
+                }
+                let frame = frame_alloc()?;
+                *pte = PageTableEntry::new(frame.ppn, PTEFlags::V);
+                self.frames.push(frame);
+            }
+            ppn = pte.ppn();
+        }
+        unreachable!()
+    }
+    fn find_pte_create(&mut self, vpn: VirtPageNum) -> Option<&mut PageTableEntry> {
+        self.walk(&vpn.indexes(), true)
+    }
+    fn find_pte(&mut self, vpn: VirtPageNum) -> Option<&mut PageTableEntry> {
+        self.walk(&vpn.indexes(), false)
+    }
+    /// 建立一个 4KiB 页映射
+    #[allow(unused)]
+    pub fn map(&mut self, vpn: VirtPageNum, ppn: PhysPageNum, flags: PTEFlags) -> MemoryResult<()> {
+        let pte = self
+            .find_pte_create(vpn)
+            .ok_or(MemoryError::MemoryNotEnough)?;
+        if pte.is_valid() {
+            return Err(PageError::PageAlreadyAlloc.into());
+        }
+        *pte = PageTableEntry::new(ppn, flags | PTEFlags::V);
+        Ok(())
+    }
+    /// 撤销一个 4KiB 页映射
+    #[allow(unused)]
+    pub fn unmap(&mut self, vpn: VirtPageNum) -> MemoryResult<()> {
+        let pte = self.find_pte(vpn).ok_or(PageError::DirPageInvalid)?;
+        if !pte.is_valid() {
+            return Err(PageError::PageInvalid.into());
+        }
+        *pte = PageTableEntry::empty();
+        Ok(())
+    }
+    /// 建立一个 2MiB 巨页映射：只走 SV39 的前两级（`idxs[..2]`），在中间级
+    /// （level 1）直接写一个叶子 PTE，覆盖 `vpn` 所在的整个 512 页对齐块。
+    /// `vpn` 必须是 2MiB 对齐的块起始 VPN，由调用方（`MapArea`）保证。
+    pub fn map_huge(&mut self, vpn: VirtPageNum, ppn: PhysPageNum, flags: PTEFlags) -> MemoryResult<()> {
+        let idxs = vpn.indexes();
+        let pte = self
+            .walk(&idxs[..2], true)
+            .ok_or(MemoryError::MemoryNotEnough)?;
+        if pte.is_valid() {
+            return Err(PageError::PageAlreadyAlloc.into());
+        }
+        *pte = PageTableEntry::new(ppn, flags | PTEFlags::V);
+        Ok(())
+    }
+    /// 撤销一个 2MiB 巨页映射
+    pub fn unmap_huge(&mut self, vpn: VirtPageNum) -> MemoryResult<()> {
+        let idxs = vpn.indexes();
+        let pte = self.walk(&idxs[..2], false).ok_or(PageError::DirPageInvalid)?;
+        if !pte.is_valid() {
+            return Err(PageError::PageInvalid.into());
+        }
+        *pte = PageTableEntry::empty();
+        Ok(())
+    }
+    /// 获取某虚拟页号对应的页表项
+    pub fn translate(&mut self, vpn: VirtPageNum) -> MemoryResult<PageTableEntry> {
+        self.find_pte(vpn)
+            .filter(|pte| pte.is_valid())
+            .map(|pte| *pte)
+            .ok_or(PageError::PageInvalid.into())
+    }
+    /// 获取页表对应的 token (satp CSR 格式)
+    pub fn token(&self) -> usize {
+        8usize << 60 | self.root_ppn.0
+    }
+}
+
+/// Translate a pointer to a mutable u8 Vec through page table to a Vec
+/// of u8 slices, where each slice stays on a single physical page.
+pub fn translated_byte_buffer(
+    token: usize,
+    ptr: *const u8,
+    len: usize,
+) -> MemoryResult<Vec<&'static mut [u8]>> {
+    let page_table = PageTable::from_token(token);
+    let mut start = ptr as usize;
+    let end = start + len;
+    let mut v = Vec::new();
+    while start < end {
+        let start_va = VirtAddr::from(start);
+        let mut vpn = start_va.floor();
+        let ppn = page_table.translate_vpn(vpn)?.ppn();
+        vpn.step();
+        let mut end_va: VirtAddr = vpn.into();
+        end_va = end_va.min(VirtAddr::from(end));
+        if end_va.page_offset() == 0 {
+            v.push(&mut ppn.get_bytes_array()[start_va.page_offset()..]);
+        } else {
+            v.push(&mut ppn.get_bytes_array()[start_va.page_offset()..end_va.page_offset()]);
+        }
+        start = end_va.into();
+    }
+    Ok(v)
+}
+
+/// Load a string from other address spaces into kernel space without
+/// an end `\0`.
+pub fn translated_str(token: usize, ptr: *const u8) -> String {
+    let page_table = PageTable::from_token(token);
+    let mut string = String::new();
+    let mut va = ptr as usize;
+    loop {
+        let ch: u8 = *(page_table
+            .translate_va(VirtAddr::from(va))
+            .unwrap()
+            .get_mut());
+        if ch == 0 {
+            break;
+        } else {
+            string.push(ch as char);
+            va += 1;
+        }
+    }
+    string
+}
+
+impl PageTable {
+    /// `find_pte` 的只读版本，供 `translated_byte_buffer`/`translated_str` 这类
+    /// 不需要为用户指针创建目录页的场景使用（来自 `from_token` 临时构造的页表，
+    /// 本就不持有 `frames`，按需创建也无处保存）。
+    fn translate_vpn(&self, vpn: VirtPageNum) -> MemoryResult<PageTableEntry> {
+        let idxs = vpn.indexes();
+        let mut ppn = self.root_ppn;
+        for (i, &idx) in idxs.iter().enumerate() {
+            let pte = &ppn.get_pte_array()[idx];
+            if i == idxs.len() - 1 {
+                return if pte.is_valid() {
+                    Ok(*pte)
+                } else {
+                    Err(PageError::PageInvalid.into())
+                };
+            }
+            if !pte.is_valid() {
+                return Err(PageError::DirPageInvalid.into());
+            }
+            ppn = pte.ppn();
+        }
+        unreachable!()
+    }
+    fn translate_va(&self, va: VirtAddr) -> MemoryResult<PhysAddr> {
+        let pte = self.translate_vpn(va.clone().floor())?;
+        let aligned_pa: PhysAddr = pte.ppn().into();
+        let offset = va.page_offset();
+        let aligned_pa_usize: usize = aligned_pa.into();
+        Ok((aligned_pa_usize + offset).into())
+    }
+}
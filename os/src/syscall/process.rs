@@ -4,17 +4,36 @@ use core::mem::size_of;
 
 use crate::{
     config::MAX_SYSCALL_NUM,
-    mm::{translated_byte_buffer, MapPermission, VirtAddr},
+    fs::{link_at, open_file, unlink_at, OpenFlags},
+    mm::{translated_byte_buffer, translated_str, MapFlags, MapPermission, ProtFlags, VirtAddr},
+    trap::TrapContext,
     task::{
         change_program_brk,
+        current_task,
         current_user_token,
+        enter_strict_syscall_filter,
+        exec_current,
         exit_current_and_run_next,
+        fork_current,
         get_start_time,
         set_syscall_times,
         get_task_status,
-        map_memory,
+        mmap_memory,
+        protect_memory,
+        ptrace_attach,
+        ptrace_detach,
+        ptrace_peek_memory,
+        ptrace_peek_registers,
+        ptrace_poke_memory,
+        ptrace_poke_registers,
+        ptrace_resume,
+        remap_memory,
+        set_priority,
+        set_syscall_filter_rule,
         unmap_memory,
         suspend_current_and_run_next,
+        waitpid_current,
+        FilterAction,
         TaskStatus
     }
 };
@@ -38,12 +57,75 @@ pub struct TaskInfo {
 }
 
 /// task exits and submit an exit code
-pub fn sys_exit(_exit_code: i32) -> ! {
+pub fn sys_exit(exit_code: i32) -> ! {
     trace!("kernel: sys_exit");
-    exit_current_and_run_next();
+    exit_current_and_run_next(exit_code);
     panic!("Unreachable in sys_exit!");
 }
 
+/// current process forks itself, returning the child's pid to the parent
+/// and 0 to the child; `-1` if the fork failed (e.g. out of physical frames)
+pub fn sys_fork() -> isize {
+    trace!("kernel: sys_fork");
+    if let Some(new_task) = fork_current() {
+        let new_pid = new_task.getpid() as isize;
+        let trap_cx = new_task.inner_exclusive_access().get_trap_cx();
+        // a0 register in the child holds fork's return value: 0
+        trap_cx.x[10] = 0;
+        new_pid
+    } else {
+        -1
+    }
+}
+
+/// replace the current process's address space with the program found at
+/// `path`
+pub fn sys_exec(path: *const u8) -> isize {
+    trace!("kernel: sys_exec");
+    let token = current_user_token();
+    let path = translated_str(token, path);
+    if let Some(app_inode) = open_file(path.as_str(), OpenFlags::RDONLY) {
+        let all_data = app_inode.read_all();
+        if exec_current(&all_data) {
+            0
+        } else {
+            -1
+        }
+    } else {
+        -1
+    }
+}
+
+/// reap an exited child matching `pid` (`-1` for any child), writing its
+/// exit code through `exit_code_ptr` when given. Returns -1 if there is no
+/// such child at all, -2 if it exists but hasn't exited yet, and its pid
+/// once reaped.
+pub fn sys_waitpid(pid: isize, exit_code_ptr: *mut i32) -> isize {
+    trace!("kernel: sys_waitpid");
+    match waitpid_current(pid) {
+        Err(()) => -1,
+        Ok(None) => -2,
+        Ok(Some((found_pid, exit_code))) => {
+            if !exit_code_ptr.is_null() {
+                if let Ok(regions) = translated_byte_buffer(
+                    current_user_token(),
+                    exit_code_ptr as *const u8,
+                    core::mem::size_of::<i32>(),
+                ) {
+                    copy(&exit_code.to_ne_bytes(), regions);
+                }
+            }
+            found_pid as isize
+        }
+    }
+}
+
+/// get the pid of the current process
+pub fn sys_getpid() -> isize {
+    trace!("kernel: sys_getpid");
+    current_task().unwrap().getpid() as isize
+}
+
 /// current task gives up resources for other tasks
 pub fn sys_yield() -> isize {
     trace!("kernel: sys_yield");
@@ -116,13 +198,40 @@ pub fn sys_mmap(start: usize, len: usize, port: usize) -> isize {
     if port & (!0x7) != 0 || port & 0x7 == 0 {
         return -1;
     }
-    let start_virtaddr: VirtAddr = start.into();
-    let end_virtaddr: VirtAddr = (start + len).into();
-    let flags = (port as u8) << 1;
-    map_memory(start_virtaddr, end_virtaddr, MapPermission::from_bits(flags).unwrap() | MapPermission::U)
+    let Some(prot) = ProtFlags::from_bits(port as u32) else {
+        return -1;
+    };
+    mmap_memory(
+        start.into(),
+        len,
+        prot,
+        MapFlags::MAP_FIXED | MapFlags::MAP_ANONYMOUS | MapFlags::MAP_POPULATE,
+    )
+}
+
+/// Like `sys_mmap`, but maps a 2MiB huge page (`MapType::HugeFramed`)
+/// instead of a run of 4KiB pages. `start`/`len` must be 2MiB-aligned.
+pub fn sys_mmap_huge(start: usize, len: usize, port: usize) -> isize {
+    trace!("kernel: sys_mmap_huge");
+    if port & (!0x7) != 0 || port & 0x7 == 0 {
+        return -1;
+    }
+    let Some(prot) = ProtFlags::from_bits(port as u32) else {
+        return -1;
+    };
+    mmap_memory(
+        start.into(),
+        len,
+        prot,
+        MapFlags::MAP_FIXED | MapFlags::MAP_ANONYMOUS | MapFlags::MAP_POPULATE | MapFlags::MAP_HUGETLB,
+    )
 }
 
 // YOUR JOB: Implement munmap.
+//
+// `unmap_memory` tears down any `Framed` area in the given range regardless
+// of how it was built, so it's already the right teardown for areas
+// `mmap_memory` constructs — there's no separate "new API" munmap to call.
 pub fn sys_munmap(start: usize, len: usize) -> isize {
     trace!("kernel: sys_munmap");
     if start % crate::config::PAGE_SIZE != 0 {
@@ -132,6 +241,177 @@ pub fn sys_munmap(start: usize, len: usize) -> isize {
     let end_virtaddr: VirtAddr = (start + len).into();
     unmap_memory(start_virtaddr, end_virtaddr)
 }
+/// resize (and, if `flags` requests it, relocate) an existing `[old_addr,
+/// old_addr + old_len)` mapping to `new_len` bytes (`sys_mremap`); `flags`
+/// bit 0 is `MREMAP_MAYMOVE`, allowing the kernel to pick a new address
+/// when it cannot grow the mapping in place. Returns the new mapping's
+/// start address, or `-1` on failure.
+pub fn sys_mremap(old_addr: usize, old_len: usize, new_len: usize, flags: usize) -> isize {
+    trace!("kernel: sys_mremap");
+    if old_addr % crate::config::PAGE_SIZE != 0 {
+        return -1;
+    }
+    const MREMAP_MAYMOVE: usize = 1 << 0;
+    let allow_move = flags & MREMAP_MAYMOVE != 0;
+    remap_memory(old_addr.into(), old_len, new_len, allow_move)
+}
+
+/// change the protection of an already-mapped `[start, start + len)` region
+/// (`sys_mprotect`); `port` is interpreted the same way as in `sys_mmap`
+pub fn sys_mprotect(start: usize, len: usize, port: usize) -> isize {
+    trace!("kernel: sys_mprotect");
+    if start % crate::config::PAGE_SIZE != 0 {
+        return -1;
+    }
+    if port & (!0x7) != 0 || port & 0x7 == 0 {
+        return -1;
+    }
+    let start_virtaddr: VirtAddr = start.into();
+    let end_virtaddr: VirtAddr = (start + len).into();
+    let flags = (port as u8) << 1;
+    protect_memory(start_virtaddr, end_virtaddr, MapPermission::from_bits(flags).unwrap() | MapPermission::U)
+}
+
+/// set the current task's stride-scheduling priority; `-1` if `prio` is
+/// below the minimum allowed priority
+pub fn sys_set_priority(prio: isize) -> isize {
+    trace!("kernel: sys_set_priority");
+    set_priority(prio)
+}
+
+/// set the current task's filter action for `syscall_id`; `kind` is 0 for
+/// `Allow`, 1 for `Errno(errno)`, 2 for `Kill`. Returns `-1` if `kind` is
+/// invalid, or if the task is in strict mode and this would loosen an
+/// existing rule.
+pub fn sys_seccomp_rule(syscall_id: usize, kind: usize, errno: isize) -> isize {
+    trace!("kernel: sys_seccomp_rule");
+    let action = match kind {
+        0 => FilterAction::Allow,
+        1 => FilterAction::Errno(errno),
+        2 => FilterAction::Kill,
+        _ => return -1,
+    };
+    set_syscall_filter_rule(syscall_id, action)
+}
+
+/// tighten the current task's syscall filter into strict mode: only ids
+/// with an explicit `Allow`/`Errno` rule survive, everything else is killed
+pub fn sys_seccomp_strict() -> isize {
+    trace!("kernel: sys_seccomp_strict");
+    enter_strict_syscall_filter();
+    0
+}
+
+/// attach to (start tracing) the current task's child `target_pid`
+pub fn sys_ptrace_attach(target_pid: usize) -> isize {
+    trace!("kernel: sys_ptrace_attach");
+    ptrace_attach(target_pid)
+}
+
+/// detach from the traced child `target_pid`, resuming it if it was stopped
+pub fn sys_ptrace_detach(target_pid: usize) -> isize {
+    trace!("kernel: sys_ptrace_detach");
+    ptrace_detach(target_pid)
+}
+
+/// resume the traced child `target_pid` from its current stop
+pub fn sys_ptrace_cont(target_pid: usize) -> isize {
+    trace!("kernel: sys_ptrace_cont");
+    ptrace_resume(target_pid)
+}
+
+/// copy the stopped traced child `target_pid`'s saved registers into `buf`
+/// (must hold at least `size_of::<TrapContext>()` bytes)
+pub fn sys_ptrace_get_regs(target_pid: usize, buf: *mut u8) -> isize {
+    trace!("kernel: sys_ptrace_get_regs");
+    const SIZE: usize = size_of::<TrapContext>();
+    match ptrace_peek_registers(target_pid) {
+        Some(regs) => {
+            if let Ok(regions) = translated_byte_buffer(current_user_token(), buf as *const u8, SIZE) {
+                let bytes =
+                    unsafe { core::slice::from_raw_parts(&regs as *const TrapContext as *const u8, SIZE) };
+                copy(bytes, regions);
+                0
+            } else {
+                -1
+            }
+        }
+        None => -1,
+    }
+}
+
+/// overwrite the stopped traced child `target_pid`'s saved registers from `buf`
+pub fn sys_ptrace_set_regs(target_pid: usize, buf: *const u8) -> isize {
+    trace!("kernel: sys_ptrace_set_regs");
+    const SIZE: usize = size_of::<TrapContext>();
+    if let Ok(regions) = translated_byte_buffer(current_user_token(), buf, SIZE) {
+        let mut raw = alloc::vec![0u8; SIZE];
+        let mut offset = 0;
+        for region in regions {
+            raw[offset..offset + region.len()].copy_from_slice(region);
+            offset += region.len();
+        }
+        let regs = unsafe { (raw.as_ptr() as *const TrapContext).read() };
+        ptrace_poke_registers(target_pid, regs)
+    } else {
+        -1
+    }
+}
+
+/// copy `len` bytes of the traced child `target_pid`'s user memory starting
+/// at `addr` into the caller's `buf`
+pub fn sys_ptrace_peek_data(target_pid: usize, addr: usize, len: usize, buf: *mut u8) -> isize {
+    trace!("kernel: sys_ptrace_peek_data");
+    match ptrace_peek_memory(target_pid, addr, len) {
+        Some(data) => {
+            if let Ok(regions) = translated_byte_buffer(current_user_token(), buf as *const u8, len) {
+                copy(&data, regions);
+                0
+            } else {
+                -1
+            }
+        }
+        None => -1,
+    }
+}
+
+/// overwrite `len` bytes of the traced child `target_pid`'s user memory
+/// starting at `addr` with the caller's `buf`
+pub fn sys_ptrace_poke_data(target_pid: usize, addr: usize, len: usize, buf: *const u8) -> isize {
+    trace!("kernel: sys_ptrace_poke_data");
+    if let Ok(regions) = translated_byte_buffer(current_user_token(), buf, len) {
+        let mut data = alloc::vec![0u8; len];
+        let mut offset = 0;
+        for region in regions {
+            data[offset..offset + region.len()].copy_from_slice(region);
+            offset += region.len();
+        }
+        ptrace_poke_memory(target_pid, addr, &data)
+    } else {
+        -1
+    }
+}
+
+/// create a hard link `new_path` pointing at the same file as `old_path`;
+/// `-1` if `old_path` and `new_path` name the same path or the link fails
+pub fn sys_linkat(old_path: *const u8, new_path: *const u8) -> isize {
+    trace!("kernel: sys_linkat");
+    let token = current_user_token();
+    let old_path = translated_str(token, old_path);
+    let new_path = translated_str(token, new_path);
+    link_at(old_path.as_str(), new_path.as_str())
+}
+
+/// remove the first entry naming `path` from the in-memory link table
+/// (`fs::unlink_at`); the file's inode and data on disk are not freed, even
+/// once no name refers to it. `-1` if no entry names `path`
+pub fn sys_unlinkat(path: *const u8) -> isize {
+    trace!("kernel: sys_unlinkat");
+    let token = current_user_token();
+    let path = translated_str(token, path);
+    unlink_at(path.as_str())
+}
+
 /// change data segment size
 pub fn sys_sbrk(size: i32) -> isize {
     trace!("kernel: sys_sbrk");
@@ -10,338 +10,443 @@
 //! might not be what you expect.
 
 mod context;
+mod pid;
+mod processor;
+mod ptrace;
+mod scheduler;
+mod seccomp;
 mod switch;
 #[allow(clippy::module_inception)]
 mod task;
 
 use crate::loader::{get_app_data, get_num_app};
-use crate::mm::{MemoryResult, MapPermission, PagePermissionError, VirtAddr};
+use crate::mm::{MapFlags, MemoryResult, MapPermission, MemorySet, PagePermissionError, ProtFlags, VirtAddr};
 use crate::sync::UPSafeCell;
 use crate::trap::TrapContext;
+use alloc::sync::Arc;
 use alloc::vec::Vec;
 use lazy_static::*;
+use scheduler::{Scheduler, StrideScheduler};
 use switch::__switch;
-pub use task::{TaskControlBlock, TaskStatus};
+pub use task::{TaskControlBlock, TaskInfo, TaskStatus};
 
 pub use context::TaskContext;
-
-/// The task manager, where all the tasks are managed.
+pub use pid::{pid_alloc, KernelStack, PidHandle};
+pub use processor::{
+    current_task, current_trap_cx, current_user_token, processor_syscall_counter, run_tasks,
+    schedule, take_current_task, SyscallDecision,
+};
+pub use scheduler::{FifoScheduler, BIG_STRIDE, MIN_PRIORITY};
+pub use seccomp::FilterAction;
+
+/// The scheduling policy [`TASK_MANAGER`] currently runs. Swapping this
+/// alias (e.g. to `FifoScheduler<Arc<TaskControlBlock>>`) is enough to
+/// switch every task's scheduling policy, since `TaskManager` only relies on
+/// the [`Scheduler`] trait.
+type ActiveScheduler = StrideScheduler;
+
+/// The ready set: tasks waiting to be scheduled, behind a pluggable
+/// [`Scheduler`] policy (stride scheduling by default, see
+/// [`ActiveScheduler`]). Replaces the old `Vec<TaskControlBlock>` indexed by
+/// a static `current_task`, so tasks can now be created (`fork`) and reaped
+/// (`waitpid`) at runtime instead of only existing as a fixed, preloaded
+/// set.
+///
+/// `TaskManager` only owns tasks that are `Ready`; the one currently
+/// running lives in [`processor::Processor`] instead, which is also where
+/// `current_user_token`/`current_trap_cx` read from. `add`/`fetch` here and
+/// `run_tasks`'s fetch-switch-suspend loop there are the two halves of the
+/// same split.
 ///
-/// Functions implemented on `TaskManager` deals with all task state transitions
-/// and task context switching. For convenience, you can find wrappers around it
-/// in the module level.
+/// The split itself landed earlier as a side effect of adding per-task
+/// kernel stacks and fork/exec/waitpid (the commit tagged chunk0-3); nothing
+/// since has needed to pull a not-yet-scheduled task back out of the ready
+/// set (there is no kill-by-pid syscall in this tree), so `TaskManager`
+/// doesn't carry a `remove`.
 ///
-/// Most of `TaskManager` are hidden behind the field `inner`, to defer
-/// borrowing checks to runtime. You can see examples on how to use `inner` in
-/// existing functions on `TaskManager`.
+/// STATUS: this request (chunk2-2) asked for the `TaskManager`/`Processor`
+/// split described above, but the split had already landed as a side effect
+/// of chunk0-3 by the time this request was picked up — the commit tagged
+/// for this request only updated this doc comment and briefly added, then
+/// deleted again, a dead `TaskManager::remove`. Credit the split to chunk0-3;
+/// this request's own diff is doc-only.
 pub struct TaskManager {
-    /// total number of tasks
-    num_app: usize,
-    /// use inner value to get mutable access
-    inner: UPSafeCell<TaskManagerInner>,
-}
-
-/// The task manager inner in 'UPSafeCell'
-struct TaskManagerInner {
-    /// task list
-    tasks: Vec<TaskControlBlock>,
-    /// id of current `Running` task
-    current_task: usize,
+    scheduler: UPSafeCell<ActiveScheduler>,
 }
 
 lazy_static! {
     /// a `TaskManager` global instance through lazy_static!
-    pub static ref TASK_MANAGER: TaskManager = {
-        println!("init TASK_MANAGER");
-        let num_app = get_num_app();
-        println!("num_app = {}", num_app);
-        let mut tasks: Vec<TaskControlBlock> = Vec::new();
-        for i in 0..num_app {
-            tasks.push(TaskControlBlock::new(get_app_data(i), i));
-        }
-        TaskManager {
-            num_app,
-            inner: unsafe {
-                UPSafeCell::new(TaskManagerInner {
-                    tasks,
-                    current_task: 0,
-                })
-            },
-        }
+    pub static ref TASK_MANAGER: TaskManager = TaskManager {
+        scheduler: unsafe { UPSafeCell::new(ActiveScheduler::new()) },
     };
 }
 
 impl TaskManager {
-    /// Run the first task in task list.
-    ///
-    /// Generally, the first task in task list is an idle task (we call it zero process later).
-    /// But in ch4, we load apps statically, so the first task is a real app.
-    fn run_first_task(&self) -> ! {
-        let mut inner = self.inner.exclusive_access();
-        let next_task = &mut inner.tasks[0];
-        next_task.task_status = TaskStatus::Running;
-        // 开始调度
-        next_task.task_info.dispatch();
-        let next_task_cx_ptr = &next_task.task_cx as *const TaskContext;
-        drop(inner);
-        let mut _unused = TaskContext::zero_init();
-        // before this, we should drop local variables that must be dropped manually
-        unsafe {
-            __switch(&mut _unused as *mut _, next_task_cx_ptr);
-        }
-        panic!("unreachable in run_first_task!");
-    }
-
-    /// Change the status of current `Running` task into `Ready`.
-    fn mark_current_suspended(&self) {
-        let mut inner = self.inner.exclusive_access();
-        let cur = inner.current_task;
-        inner.tasks[cur].task_status = TaskStatus::Ready;
+    /// Add a task to the ready set.
+    pub fn add(&self, task: Arc<TaskControlBlock>) {
+        self.scheduler.exclusive_access().insert(task);
     }
 
-    /// Change the status of current `Running` task into `Exited`.
-    fn mark_current_exited(&self) {
-        let mut inner = self.inner.exclusive_access();
-        let cur = inner.current_task;
-        inner.tasks[cur].task_status = TaskStatus::Exited;
+    /// Pop the next task the current scheduling policy picks, if any.
+    pub fn fetch(&self) -> Option<Arc<TaskControlBlock>> {
+        self.scheduler.exclusive_access().pop()
     }
+}
 
-    /// Find next task to run and return task id.
-    ///
-    /// In this case, we only return the first `Ready` task in task list.
-    fn find_next_task(&self) -> Option<usize> {
-        let inner = self.inner.exclusive_access();
-        let current = inner.current_task;
-        (current + 1..current + self.num_app + 1)
-            .map(|id| id % self.num_app)
-            .find(|id| inner.tasks[*id].task_status == TaskStatus::Ready)
-    }
+/// Add a task to the ready queue.
+pub fn add_task(task: Arc<TaskControlBlock>) {
+    TASK_MANAGER.add(task);
+}
 
-    /// Get the current 'Running' task's token.
-    fn get_current_token(&self) -> usize {
-        let inner = self.inner.exclusive_access();
-        inner.tasks[inner.current_task].get_user_token()
-    }
+/// Pop a task from the ready queue.
+pub fn fetch_task() -> Option<Arc<TaskControlBlock>> {
+    TASK_MANAGER.fetch()
+}
 
-    /// Get the current 'Running' task's trap contexts.
-    fn get_current_trap_cx(&self) -> &'static mut TrapContext {
-        let inner = self.inner.exclusive_access();
-        inner.tasks[inner.current_task].get_trap_cx()
+/// Load every statically-linked app as an initial `Ready` task; this keeps
+/// the existing multiprogramming demo apps working on top of the new
+/// process model (real programs now come from `sys_fork`/`sys_exec`
+/// instead).
+pub fn add_initial_tasks() {
+    let num_app = get_num_app();
+    for i in 0..num_app {
+        add_task(Arc::new(TaskControlBlock::new(get_app_data(i))));
     }
+}
 
-    /// Change the current 'Running' task's program break
-    pub fn change_current_program_brk(&self, size: i32) -> Option<usize> {
-        let mut inner = self.inner.exclusive_access();
-        let cur = inner.current_task;
-        inner.tasks[cur].change_program_brk(size)
-    }
+/// Run the first task fetched from the ready queue.
+pub fn run_first_task() {
+    add_initial_tasks();
+    run_tasks();
+}
 
-    /// Switch current `Running` task to the task we have found,
-    /// or there is no `Ready` task and we can exit with all applications completed
-    fn run_next_task(&self) {
-        if let Some(next) = self.find_next_task() {
-            let mut inner = self.inner.exclusive_access();
-            let current = inner.current_task;
-            inner.tasks[next].task_status = TaskStatus::Running;
-            inner.current_task = next;
-            // 开始调度
-            inner.tasks[next].task_info.dispatch();
-            let current_task_cx_ptr = &mut inner.tasks[current].task_cx as *mut TaskContext;
-            let next_task_cx_ptr = &inner.tasks[next].task_cx as *const TaskContext;
-            drop(inner);
-            // before this, we should drop local variables that must be dropped manually
-            unsafe {
-                __switch(current_task_cx_ptr, next_task_cx_ptr);
-            }
-            // go back to user mode
-        } else {
-            panic!("All applications completed!");
-        }
-    }
+/// Suspend the current `Running` task (push it back onto the ready queue as
+/// `Ready`) and run the next task in the ready queue.
+pub fn suspend_current_and_run_next() {
+    let task = take_current_task().unwrap();
+    let mut task_inner = task.inner_exclusive_access();
+    let task_cx_ptr = &mut task_inner.task_cx as *mut TaskContext;
+    task_inner.task_status = TaskStatus::Ready;
+    drop(task_inner);
+    add_task(task);
+    schedule(task_cx_ptr);
+}
 
-    // 获取当前任务状态
-    fn get_task_status(&self) -> TaskStatus {
-        let inner = self.inner.exclusive_access();
-        let current = inner.current_task;
-        inner.tasks[current].task_status
-    }
+/// Exit the current `Running` task with `exit_code` and run the next task
+/// in the ready queue. Its children are not reparented onto anything; their
+/// `parent` link is simply left dangling (children.clear() drops the
+/// parent's references to them, not the other way around), so they become
+/// unreachable by any future `sys_waitpid` and are dropped once their own
+/// `Arc`s refcount to zero.
+pub fn exit_current_and_run_next(exit_code: i32) {
+    let task = take_current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    inner.task_status = TaskStatus::Exited;
+    inner.exit_code = exit_code;
+    // children become orphans; dropping their `parent` link here would be
+    // enough, but leaving it dangling (via `Weak`) is also harmless since
+    // nobody will `sys_waitpid` on them again.
+    inner.children.clear();
+    inner.memory_set = MemorySet::new_bare().expect("failed to release exited address space");
+    drop(inner);
+    drop(task);
+    let mut _unused = TaskContext::zero_init();
+    schedule(&mut _unused as *mut _);
+}
 
-    /// 获取调度起始时间
-    pub fn get_start_time(&self) -> usize {
-        let inner = self.inner.exclusive_access();
-        let current = inner.current_task;
-        inner.tasks[current].task_info.start_time
-    }
+/// Fork the current task, returning the child's pid; the child itself will
+/// observe a return value of 0 once it is scheduled (set by `sys_fork`).
+///
+/// Returns `None` if the fork failed (e.g. out of physical frames), in which
+/// case no child is created and the parent is unaffected.
+pub fn fork_current() -> Option<Arc<TaskControlBlock>> {
+    let current = current_task().unwrap();
+    let new_task = current.fork()?;
+    add_task(Arc::clone(&new_task));
+    Some(new_task)
+}
 
-    // syscall 调用次数的映射表
-    fn set_syscall_times(&self, syscalls: &mut [u32; crate::config::MAX_SYSCALL_NUM]) {
-        let inner = self.inner.exclusive_access();
-        let current = inner.current_task;
-        for (id, n) in inner.tasks[current].task_info.syscall_times.iter() {
-            syscalls[*id] = *n;
-        }
-    }
+/// Replace the current task's address space with `elf_data` (`sys_exec`),
+/// returning whether it succeeded.
+pub fn exec_current(elf_data: &[u8]) -> bool {
+    current_task().unwrap().exec(elf_data)
+}
 
-    /// 针对 id 的 syscall 调用次数计数器
-    pub fn syscall_counter(&self, syscall_id: usize) {
-        let mut inner = self.inner.exclusive_access();
-        let current = inner.current_task;
-        let times = &mut inner.tasks[current].task_info.syscall_times;
-        // 保存每个 syscall 的调用次数, 谨防 syscall_id 无效
-        *times.entry(syscall_id).or_default() += 1;
+/// Reap an exited child of the current task matching `pid` (or any child if
+/// `pid == -1`), returning `(child_pid, exit_code)`.
+///
+/// Returns `Ok(None)` if a match exists but hasn't exited yet, and
+/// `Err(())` if the current task has no matching child at all.
+pub fn waitpid_current(pid: isize) -> Result<Option<(usize, i32)>, ()> {
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    if !inner
+        .children
+        .iter()
+        .any(|child| pid == -1 || pid as usize == child.getpid())
+    {
+        return Err(());
     }
-
-    /// 虚拟内存与物理内存的映射
-    fn map_memory(&self, start_virtaddr: VirtAddr, end_virtaddr: VirtAddr, permission: MapPermission) -> isize {
-        let mut inner = self.inner.exclusive_access();
-        let current = inner.current_task;
-        let memset = &mut inner.tasks[current].memory_set;
-        if memset.map_memory(start_virtaddr, end_virtaddr, permission).is_ok() {
-            0
-        } else {
-            -1
-        }
+    let child_index = inner.children.iter().position(|child| {
+        child.inner_exclusive_access().is_zombie() && (pid == -1 || pid as usize == child.getpid())
+    });
+    if let Some(index) = child_index {
+        let child = inner.children.remove(index);
+        // make sure this is the only remaining reference
+        assert_eq!(Arc::strong_count(&child), 1);
+        let found_pid = child.getpid();
+        let exit_code = child.inner_exclusive_access().exit_code;
+        Ok(Some((found_pid, exit_code)))
+    } else {
+        Ok(None)
     }
+}
 
-    /// 取消映射
-    fn unmap_memory(&self, start_virtaddr: VirtAddr, end_virtaddr: VirtAddr) -> isize {
-        let mut inner = self.inner.exclusive_access();
-        let current = inner.current_task;
-        let memset = &mut inner.tasks[current].memory_set;
-        if memset.unmap_memory(start_virtaddr, end_virtaddr).is_ok() {
-            0
-        } else {
-            -1
-        }
-    }
+/// Change the current 'Running' task's program break
+pub fn change_program_brk(size: i32) -> Option<usize> {
+    current_task().unwrap().change_program_brk(size)
+}
 
-    /// 检查可读性
-    pub fn check_readable(&self, va: VirtAddr) -> MemoryResult<()> {
-        let mut inner = self.inner.exclusive_access();
-        let current = inner.current_task;
-        let memset = &mut inner.tasks[current].memory_set;
-        let a = memset.translate(va.floor())?;
-        if a.readable() {
-            Ok(())
-        } else {
-            Err(PagePermissionError::Unreadable.into())
-        }
+/// Set the current task's stride-scheduling priority (`sys_set_priority`).
+/// Returns the priority that was applied, or `-1` if `prio` isn't a valid
+/// priority (i.e. below `scheduler::MIN_PRIORITY`).
+pub fn set_priority(prio: isize) -> isize {
+    if prio < MIN_PRIORITY as isize {
+        return -1;
     }
-    /// 检查可写性
-    pub fn check_writeable(&self, va: VirtAddr) -> MemoryResult<()> {
-        let mut inner = self.inner.exclusive_access();
-        let current = inner.current_task;
-        let memset = &mut inner.tasks[current].memory_set;
-        let a = memset.translate(va.floor())?;
-        if a.readable() && a.writable() {
-            Ok(())
-        } else {
-            Err(PagePermissionError::Unwritable.into())
-        }
+    match current_task().unwrap().set_priority(prio as usize) {
+        Some(applied) => applied as isize,
+        None => -1,
     }
+}
 
-    /// 检查可执行性
-    pub fn check_executable(&self, va: VirtAddr) -> MemoryResult<()> {
-        let mut inner = self.inner.exclusive_access();
-        let current = inner.current_task;
-        let memset = &mut inner.tasks[current].memory_set;
-        let a = memset.translate(va.floor())?;
-        if a.readable() && a.executable() {
-            Ok(())
-        } else {
-            Err(PagePermissionError::Unexecutable.into())
-        }
-    }
+/// 获取当前任务状态
+pub fn get_task_status() -> TaskStatus {
+    current_task().unwrap().inner_exclusive_access().task_status
 }
 
-/// Run the first task in task list.
-pub fn run_first_task() {
-    TASK_MANAGER.run_first_task();
+/// 获取调度起始时间
+pub fn get_start_time() -> usize {
+    current_task().unwrap().inner_exclusive_access().task_info.start_time
 }
 
-/// Switch current `Running` task to the task we have found,
-/// or there is no `Ready` task and we can exit with all applications completed
-fn run_next_task() {
-    TASK_MANAGER.run_next_task();
+/// 设置 syscall 调用次数
+pub fn set_syscall_times(syscalls: &mut [u32; crate::config::MAX_SYSCALL_NUM]) {
+    let task = current_task().unwrap();
+    let inner = task.inner_exclusive_access();
+    for (id, n) in inner.task_info.syscall_times.iter() {
+        syscalls[*id] = *n;
+    }
 }
 
-/// Change the status of current `Running` task into `Ready`.
-fn mark_current_suspended() {
-    TASK_MANAGER.mark_current_suspended();
+/// 针对 id 的系统调用计数器；同时查询当前任务的 syscall 过滤规则，若结果为
+/// `Kill` 则直接终止当前任务；否则，若当前任务被跟踪，则在此处（仅
+/// syscall 入口，没有出口侧）将其停住等待 tracer。出口侧需要 trap 分发器
+/// 在 syscall 返回处再调用一次 `stop_current_if_traced`，但本仓库这一切片
+/// 没有 `trap` 模块可以承载那个调用点，所以跟踪目前只在入口生效。调用方
+/// 据此判断是否还需要真正执行这个 syscall。
+pub fn syscall_counter(syscall_id: usize) -> SyscallDecision {
+    let decision = processor_syscall_counter(syscall_id);
+    if let SyscallDecision::Killed = decision {
+        exit_current_and_run_next(-1);
+    } else {
+        stop_current_if_traced();
+    }
+    decision
 }
 
-/// Change the status of current `Running` task into `Exited`.
-fn mark_current_exited() {
-    TASK_MANAGER.mark_current_exited();
+/// Stop the current task if it has a tracer attached, switching away to the
+/// idle control flow until its tracer calls [`ptrace_resume`]. No-op (and
+/// returns `false`) if the task isn't traced.
+pub fn stop_current_if_traced() -> bool {
+    let task = current_task().unwrap();
+    if !ptrace::stop_if_traced(&task) {
+        return false;
+    }
+    let task = take_current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    let task_cx_ptr = &mut inner.task_cx as *mut TaskContext;
+    drop(inner);
+    drop(task);
+    schedule(task_cx_ptr);
+    true
 }
 
-/// Suspend the current 'Running' task and run the next task in task list.
-pub fn suspend_current_and_run_next() {
-    mark_current_suspended();
-    run_next_task();
+/// Attach the current task to its child `target_pid` as tracer (`-1` if
+/// `target_pid` isn't a live child).
+pub fn ptrace_attach(target_pid: usize) -> isize {
+    match ptrace::attach(&current_task().unwrap(), target_pid) {
+        Some(_) => 0,
+        None => -1,
+    }
 }
 
-/// Exit the current 'Running' task and run the next task in task list.
-pub fn exit_current_and_run_next() {
-    mark_current_exited();
-    run_next_task();
+/// Detach from the current task's traced child `target_pid`, resuming it if
+/// it was stopped (`-1` if `target_pid` isn't a known child).
+pub fn ptrace_detach(target_pid: usize) -> isize {
+    match ptrace::lookup(&current_task().unwrap(), target_pid) {
+        Some(tracee) => {
+            ptrace::detach(&tracee);
+            0
+        }
+        None => -1,
+    }
 }
 
-/// Get the current 'Running' task's token.
-pub fn current_user_token() -> usize {
-    TASK_MANAGER.get_current_token()
+/// Resume the current task's stopped child `target_pid` (`-1` if
+/// `target_pid` isn't a known child).
+pub fn ptrace_resume(target_pid: usize) -> isize {
+    match ptrace::lookup(&current_task().unwrap(), target_pid) {
+        Some(tracee) => {
+            ptrace::resume(&tracee);
+            0
+        }
+        None => -1,
+    }
 }
 
-/// Get the current 'Running' task's trap contexts.
-pub fn current_trap_cx() -> &'static mut TrapContext {
-    TASK_MANAGER.get_current_trap_cx()
+/// Read the stopped child `target_pid`'s saved user registers.
+pub fn ptrace_peek_registers(target_pid: usize) -> Option<TrapContext> {
+    let tracee = ptrace::lookup(&current_task().unwrap(), target_pid)?;
+    Some(ptrace::peek_registers(&tracee))
 }
 
-/// Change the current 'Running' task's program break
-pub fn change_program_brk(size: i32) -> Option<usize> {
-    TASK_MANAGER.change_current_program_brk(size)
+/// Overwrite the stopped child `target_pid`'s saved user registers (`-1` if
+/// `target_pid` isn't a known child).
+pub fn ptrace_poke_registers(target_pid: usize, regs: TrapContext) -> isize {
+    match ptrace::lookup(&current_task().unwrap(), target_pid) {
+        Some(tracee) => {
+            ptrace::poke_registers(&tracee, regs);
+            0
+        }
+        None => -1,
+    }
 }
 
-/// 获取当前任务状态
-pub fn get_task_status() -> TaskStatus {
-    TASK_MANAGER.get_task_status()
+/// Read `len` bytes of the stopped child `target_pid`'s user memory
+/// starting at `addr`.
+pub fn ptrace_peek_memory(target_pid: usize, addr: usize, len: usize) -> Option<Vec<u8>> {
+    let tracee = ptrace::lookup(&current_task().unwrap(), target_pid)?;
+    ptrace::peek_memory(&tracee, addr, len)
 }
 
-/// 获取调度起始时间
-pub fn get_start_time() -> usize {
-    TASK_MANAGER.get_start_time()
+/// Overwrite the stopped child `target_pid`'s user memory starting at
+/// `addr` with `data` (`-1` if `target_pid` isn't a known child or the
+/// write doesn't fit the tracee's address space).
+pub fn ptrace_poke_memory(target_pid: usize, addr: usize, data: &[u8]) -> isize {
+    match ptrace::lookup(&current_task().unwrap(), target_pid) {
+        Some(tracee) => {
+            if ptrace::poke_memory(&tracee, addr, data) {
+                0
+            } else {
+                -1
+            }
+        }
+        None => -1,
+    }
 }
 
-/// 设置 syscall 调用次数
-pub fn set_syscall_times(syscalls: &mut [u32; crate::config::MAX_SYSCALL_NUM]) {
-    TASK_MANAGER.set_syscall_times(syscalls)
+/// Install/overwrite the current task's filter rule for `syscall_id`
+/// (`Allow`, `Errno(code)`, or `Kill`). Returns `-1` if the task is in
+/// strict mode and this would loosen an existing rule.
+pub fn set_syscall_filter_rule(syscall_id: usize, action: FilterAction) -> isize {
+    match current_task()
+        .unwrap()
+        .set_syscall_filter_rule(syscall_id, action)
+    {
+        Ok(()) => 0,
+        Err(()) => -1,
+    }
 }
 
-/// 针对 id 的系统调用计数器
-pub fn syscall_counter(syscall_id: usize) {
-    TASK_MANAGER.syscall_counter(syscall_id);
+/// Tighten the current task's syscall filter into strict mode: the default
+/// action becomes `Kill`, so only syscall ids with an explicit rule survive.
+pub fn enter_strict_syscall_filter() {
+    current_task().unwrap().enter_strict_syscall_filter();
 }
 
-/// 虚拟内存与物理内存的映射
-pub fn map_memory(start_virtaddr: VirtAddr, end_virtaddr: VirtAddr, permission: MapPermission) -> isize {
-    TASK_MANAGER.map_memory(start_virtaddr, end_virtaddr, permission)
+/// 按 Linux 风格的 `ProtFlags`/`MapFlags` 建立映射（`sys_mmap`/`sys_mmap_huge`），
+/// 成功返回 `0`，失败返回 `-1`——调用方一直传入 `MAP_FIXED`，映射地址由调用
+/// 者指定、本就已知，这里延续本内核 `sys_munmap`/`sys_mprotect` 等的成功约定
+/// 而不是回传地址。这是 [`MemorySet::mmap`] 的调用入口，`map_memory`
+/// 之前被 `sys_mmap` 直接调用，跳过了这层标志位翻译。
+pub fn mmap_memory(addr: VirtAddr, len: usize, prot: ProtFlags, flags: MapFlags) -> isize {
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    match inner.memory_set.mmap(addr, len, prot, flags) {
+        Ok(_) => 0,
+        Err(_) => -1,
+    }
 }
 
 /// 取消映射
 pub fn unmap_memory(start_virtaddr: VirtAddr, end_virtaddr: VirtAddr) -> isize {
-    TASK_MANAGER.unmap_memory(start_virtaddr, end_virtaddr)
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    if inner.memory_set.unmap_memory(start_virtaddr, end_virtaddr).is_ok() {
+        0
+    } else {
+        -1
+    }
+}
+
+/// 调整（可能移动）一段已有映射的大小（`sys_mremap`）
+pub fn remap_memory(old_start_va: VirtAddr, old_len: usize, new_len: usize, allow_move: bool) -> isize {
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    match inner.memory_set.mremap(old_start_va, old_len, new_len, allow_move) {
+        Ok(new_start_va) => new_start_va.0 as isize,
+        Err(_) => -1,
+    }
+}
+
+/// 修改内存保护权限（`sys_mprotect`）
+pub fn protect_memory(start_virtaddr: VirtAddr, end_virtaddr: VirtAddr, permission: MapPermission) -> isize {
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    if inner
+        .memory_set
+        .protect_memory(start_virtaddr, end_virtaddr, permission)
+        .is_ok()
+    {
+        0
+    } else {
+        -1
+    }
 }
 
 /// 检查可读性
 pub fn check_readable(va: VirtAddr) -> MemoryResult<()> {
-    TASK_MANAGER.check_readable(va)
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    let a = inner.memory_set.translate(va.floor())?;
+    if a.readable() {
+        Ok(())
+    } else {
+        Err(PagePermissionError::Unreadable.into())
+    }
 }
+
 /// 检查可写性
 pub fn check_writeable(va: VirtAddr) -> MemoryResult<()> {
-    TASK_MANAGER.check_writeable(va)
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    let a = inner.memory_set.translate(va.floor())?;
+    if a.readable() && a.writable() {
+        Ok(())
+    } else {
+        Err(PagePermissionError::Unwritable.into())
+    }
 }
 
 /// 检查可执行性
 pub fn check_executable(va: VirtAddr) -> MemoryResult<()> {
-    TASK_MANAGER.check_executable(va)
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    let a = inner.memory_set.translate(va.floor())?;
+    if a.readable() && a.executable() {
+        Ok(())
+    } else {
+        Err(PagePermissionError::Unexecutable.into())
+    }
 }
\ No newline at end of file
@@ -0,0 +1,136 @@
+//! Recyclable pid allocator and per-task kernel stack management.
+//!
+//! Kernel stacks used to live at a fixed offset from `TRAMPOLINE` keyed by a
+//! static app id (see the old `kernel_stack_position(app_id)`). Now that
+//! tasks are created and destroyed dynamically (`fork`/`exit`), the stack
+//! has to be keyed by a recyclable pid instead, and mapped/unmapped from
+//! `KERNEL_SPACE` on allocation/drop rather than once at boot.
+
+use crate::config::{KERNEL_STACK_SIZE, PAGE_SIZE, TRAMPOLINE};
+use crate::mm::{MapPermission, VirtAddr, KERNEL_SPACE};
+use crate::sync::UPSafeCell;
+use alloc::vec::Vec;
+use lazy_static::*;
+
+/// A simple stack-based allocator that recycles freed ids before handing out
+/// new ones, so pids (and kernel stack slots) don't grow unboundedly as
+/// tasks come and go.
+pub struct RecycleAllocator {
+    current: usize,
+    recycled: Vec<usize>,
+}
+
+impl RecycleAllocator {
+    /// Create an empty allocator starting from id 0.
+    pub fn new() -> Self {
+        RecycleAllocator {
+            current: 0,
+            recycled: Vec::new(),
+        }
+    }
+
+    /// Hand out the most recently recycled id, or a fresh one if none has
+    /// been recycled.
+    pub fn alloc(&mut self) -> usize {
+        if let Some(id) = self.recycled.pop() {
+            id
+        } else {
+            self.current += 1;
+            self.current - 1
+        }
+    }
+
+    /// Return an id to the pool so it can be reused.
+    pub fn dealloc(&mut self, id: usize) {
+        assert!(id < self.current);
+        assert!(
+            !self.recycled.iter().any(|i| *i == id),
+            "id {} has been deallocated twice!",
+            id
+        );
+        self.recycled.push(id);
+    }
+}
+
+lazy_static! {
+    static ref PID_ALLOCATOR: UPSafeCell<RecycleAllocator> =
+        unsafe { UPSafeCell::new(RecycleAllocator::new()) };
+}
+
+/// RAII handle around an allocated pid; automatically recycles it on drop.
+pub struct PidHandle(pub usize);
+
+impl Drop for PidHandle {
+    fn drop(&mut self) {
+        PID_ALLOCATOR.exclusive_access().dealloc(self.0);
+    }
+}
+
+/// Allocate a new pid.
+pub fn pid_alloc() -> PidHandle {
+    PidHandle(PID_ALLOCATOR.exclusive_access().alloc())
+}
+
+/// Return (bottom, top) of the kernel stack belonging to `pid` in kernel
+/// space, guard-paged from its neighbours the same way the old
+/// `app_id`-keyed scheme was.
+pub fn kernel_stack_position(pid: usize) -> (usize, usize) {
+    let top = TRAMPOLINE - pid * (KERNEL_STACK_SIZE + PAGE_SIZE);
+    let bottom = top - KERNEL_STACK_SIZE;
+    (bottom, top)
+}
+
+/// A task's kernel stack, mapped into `KERNEL_SPACE` for the lifetime of the
+/// `KernelStack` value and reclaimed on drop.
+pub struct KernelStack {
+    pid: usize,
+}
+
+impl KernelStack {
+    /// Map a fresh kernel stack for `pid_handle`.
+    pub fn new(pid_handle: &PidHandle) -> Self {
+        let pid = pid_handle.0;
+        let (kernel_stack_bottom, kernel_stack_top) = kernel_stack_position(pid);
+        KERNEL_SPACE
+            .exclusive_access()
+            .insert_framed_area(
+                kernel_stack_bottom.into(),
+                kernel_stack_top.into(),
+                MapPermission::R | MapPermission::W,
+            )
+            .expect("failed to map kernel stack");
+        KernelStack { pid }
+    }
+
+    /// Push a value onto the top of this kernel stack and return its
+    /// address, used to seed the initial `TrapContext`/`TaskContext`.
+    #[allow(unused)]
+    pub fn push_on_top<T>(&self, value: T) -> *mut T
+    where
+        T: Sized,
+    {
+        let kernel_stack_top = self.get_top();
+        let ptr_mut = (kernel_stack_top - core::mem::size_of::<T>()) as *mut T;
+        unsafe {
+            *ptr_mut = value;
+        }
+        ptr_mut
+    }
+
+    /// Top address of this kernel stack.
+    pub fn get_top(&self) -> usize {
+        let (_, kernel_stack_top) = kernel_stack_position(self.pid);
+        kernel_stack_top
+    }
+}
+
+impl Drop for KernelStack {
+    fn drop(&mut self) {
+        let (kernel_stack_bottom, kernel_stack_top) = kernel_stack_position(self.pid);
+        let kernel_stack_bottom_va: VirtAddr = kernel_stack_bottom.into();
+        KERNEL_SPACE
+            .exclusive_access()
+            .unmap_memory(kernel_stack_bottom_va, kernel_stack_top.into())
+            .expect("failed to unmap kernel stack");
+    }
+}
@@ -4,16 +4,35 @@
 //! the current running state of CPU is recorded,
 //! and the replacement and transfer of control flow of different applications are executed.
 
-use super::{__switch, TaskInfo};
+use super::__switch;
+use super::seccomp::FilterAction;
 use super::{fetch_task, TaskStatus};
 use super::{TaskContext, TaskControlBlock};
 use crate::sync::UPSafeCell;
-use crate::timer::get_time_ms;
 use crate::trap::TrapContext;
 use alloc::sync::Arc;
 use lazy_static::*;
 
-/// Processor management structure
+/// What the trap dispatcher should do with the syscall it just intercepted,
+/// decided by consulting the current task's `syscall_filter` right where
+/// [`Processor::syscall_counter`] already records it for accounting.
+pub enum SyscallDecision {
+    /// Run the syscall as requested.
+    Run,
+    /// Don't run the syscall; report this value as its result instead.
+    Errno(isize),
+    /// The task's filter says to kill it; the syscall must not run. The
+    /// caller (`syscall_counter` in `task/mod.rs`) is the one that actually
+    /// tears the task down, since that needs `PROCESSOR` released first.
+    Killed,
+}
+
+/// Processor management structure: tracks the one task currently running on
+/// this core plus the idle control flow `__switch`es back to between tasks.
+/// Counterpart to [`super::TaskManager`], which only holds the `Ready` tasks
+/// waiting to be picked; once `fetch_task` hands one over, it moves here as
+/// `current` until it's suspended (and pushed back onto the ready set) or
+/// exits.
 pub struct Processor {
     ///The task currently executing on the current processor
     current: Option<Arc<TaskControlBlock>>,
@@ -45,44 +64,19 @@ impl Processor {
     pub fn current(&self) -> Option<Arc<TaskControlBlock>> {
         self.current.as_ref().map(Arc::clone)
     }
-    /// update_task_info
-    pub fn update_task_info(&mut self, syscall: usize, add_flag: bool) {
+
+    /// 针对 id 的 syscall 调用次数计数器，记在当前运行任务的 `task_info` 上，
+    /// 并据此任务的 `syscall_filter` 返回该 syscall 应当如何处理
+    pub fn syscall_counter(&mut self, syscall_id: usize) -> SyscallDecision {
         let binding = self.current().unwrap();
         let mut task = binding.inner_exclusive_access();
-        let task_status = task.task_status;
-        task.task_info.set_status(task_status);
-        if add_flag {
-            task.task_info.syscall_counter(syscall);
+        *task.task_info.syscall_times.entry(syscall_id).or_default() += 1;
+        match task.syscall_filter.action(syscall_id) {
+            FilterAction::Allow => SyscallDecision::Run,
+            FilterAction::Errno(code) => SyscallDecision::Errno(code),
+            FilterAction::Kill => SyscallDecision::Killed,
         }
     }
-    /// get_current_task_info
-    pub fn get_current_task_info(&mut self) -> TaskInfo {
-        self.update_task_info(0,false);
-        let binding = self.current().unwrap();
-        let mut task = binding.inner_exclusive_access();
-        let start_time = task.start_time;
-        let dispatch_time = get_time_ms()-start_time;
-        println!("[Kernel][Task] get_time_ms = {}", get_time_ms());
-        println!("[Kernel][Task] start_time = {}", start_time);
-        println!("[Kernel][Task] dispatch_time = {}", dispatch_time);
-        task.task_info.set_dispatch_time(dispatch_time);
-        let task_info = task.task_info;
-        task_info
-    }
-    /// current_task_mmap
-    pub fn get_current_task_mmap(&mut self, start: usize, len: usize, port: usize) -> isize {
-        println!("[Kernel][task/mod]mmap");
-        let binding = self.current().unwrap();
-        let mut task = binding.inner_exclusive_access();
-        task.mmap(start, len, port)
-    }
-    /// current_task_munmap
-    pub fn get_current_task_munmap(&mut self, start: usize, len: usize) -> isize {
-        println!("[Kernel][task/mod]munmap");
-        let binding = self.current().unwrap();
-        let mut task = binding.inner_exclusive_access();
-        task.munmap(start, len)
-    }
 }
 
 lazy_static! {
@@ -100,6 +94,7 @@ pub fn run_tasks() {
             let mut task_inner = task.inner_exclusive_access();
             let next_task_cx_ptr = &task_inner.task_cx as *const TaskContext;
             task_inner.task_status = TaskStatus::Running;
+            task_inner.task_info.dispatch();
             // release coming task_inner manually
             drop(task_inner);
             // release coming task TCB manually
@@ -111,6 +106,7 @@ pub fn run_tasks() {
             }
         } else {
             warn!("no tasks available in run_tasks");
+            return;
         }
     }
 }
@@ -149,22 +145,7 @@ pub fn schedule(switched_task_cx_ptr: *mut TaskContext) {
     }
 }
 
-/// get_current_processor_info
-pub fn get_current_processor_info() -> TaskInfo {
-    PROCESSOR.exclusive_access().get_current_task_info()
-}
-
-/// add_processor_syscall_times
-pub fn processor_syscall_counter(syscall: usize){
-    PROCESSOR.exclusive_access().update_task_info(syscall, true);
-}
-
-/// current_processor_m_map
-pub fn get_current_processor_mmap(start: usize, len: usize, port: usize) -> isize {
-    PROCESSOR.exclusive_access().get_current_task_mmap(start, len, port)
+/// 针对 id 的系统调用计数器，委托给当前 `Processor` 上运行的任务
+pub fn processor_syscall_counter(syscall_id: usize) -> SyscallDecision {
+    PROCESSOR.exclusive_access().syscall_counter(syscall_id)
 }
-
-/// current_processor_m_unmap
-pub fn get_current_processor_munmap(start: usize, len: usize) -> isize {
-    PROCESSOR.exclusive_access().get_current_task_munmap(start, len)
-}
\ No newline at end of file
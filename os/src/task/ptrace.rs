@@ -0,0 +1,127 @@
+//! Minimal ptrace-style tracing layered on [`super::TaskManager`]: one task
+//! (the tracer) attaches to one of its own children (the tracee), stops it
+//! at syscall entry, and inspects/modifies its registers and memory while
+//! it's stopped.
+//!
+//! Entry-only, not entry/exit: stopping again on the way out of a syscall
+//! needs a second `stop_if_traced` call from wherever the syscall-return
+//! path lives, and this tree has no `trap` module to host that call. See
+//! [`stop_if_traced`]'s doc comment.
+//!
+//! Tracing is scoped to parent/child pairs because that's the only
+//! pid-indexed task lookup this crate has (see `waitpid_current`'s scan of
+//! `children`); there is no global pid registry to reach an unrelated task.
+
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use super::task::TaskControlBlock;
+use super::TaskStatus;
+use crate::mm::translated_byte_buffer;
+use crate::trap::TrapContext;
+
+/// Find `pid` among `tracer`'s children, the only tasks it's allowed to
+/// trace.
+fn find_child(tracer: &Arc<TaskControlBlock>, pid: usize) -> Option<Arc<TaskControlBlock>> {
+    tracer
+        .inner_exclusive_access()
+        .children
+        .iter()
+        .find(|child| child.getpid() == pid)
+        .cloned()
+}
+
+/// Attach `tracer` to its child `target_pid`, marking it traced. Fails if
+/// no such child exists or it has already exited.
+pub fn attach(tracer: &Arc<TaskControlBlock>, target_pid: usize) -> Option<Arc<TaskControlBlock>> {
+    let target = find_child(tracer, target_pid)?;
+    let mut inner = target.inner_exclusive_access();
+    if inner.is_zombie() {
+        return None;
+    }
+    inner.tracer = Some(Arc::downgrade(tracer));
+    drop(inner);
+    Some(target)
+}
+
+/// Detach from `tracee`, clearing its tracer and resuming it if it was
+/// stopped waiting on one, so detaching never stands a task up.
+pub fn detach(tracee: &Arc<TaskControlBlock>) {
+    let mut inner = tracee.inner_exclusive_access();
+    let was_stopped = inner.task_status == TaskStatus::Stopped;
+    inner.tracer = None;
+    drop(inner);
+    if was_stopped {
+        resume(tracee);
+    }
+}
+
+/// Called at the syscall-interception point (see `Processor::syscall_counter`),
+/// once at syscall entry. There's no matching exit-side call in this tree:
+/// that would need the trap dispatcher's syscall-return path, and this repo
+/// slice has no `trap` module to host it. If `task` has a tracer attached,
+/// park it in `Stopped` so `run_tasks` can never pick it back up until its
+/// tracer calls [`resume`]. Returns whether it stopped.
+pub fn stop_if_traced(task: &Arc<TaskControlBlock>) -> bool {
+    let mut inner = task.inner_exclusive_access();
+    if inner.tracer.is_some() {
+        inner.task_status = TaskStatus::Stopped;
+        true
+    } else {
+        false
+    }
+}
+
+/// Resume a stopped tracee: back to `Ready` and re-inserted into the
+/// scheduler, the only way a traced task re-enters the ready set.
+pub fn resume(tracee: &Arc<TaskControlBlock>) {
+    let mut inner = tracee.inner_exclusive_access();
+    inner.task_status = TaskStatus::Ready;
+    drop(inner);
+    super::add_task(Arc::clone(tracee));
+}
+
+/// Read the stopped tracee's saved user registers.
+pub fn peek_registers(tracee: &Arc<TaskControlBlock>) -> TrapContext {
+    *tracee.inner_exclusive_access().get_trap_cx()
+}
+
+/// Overwrite the stopped tracee's saved user registers.
+pub fn poke_registers(tracee: &Arc<TaskControlBlock>, regs: TrapContext) {
+    *tracee.inner_exclusive_access().get_trap_cx() = regs;
+}
+
+/// Read `len` bytes of the tracee's user memory starting at `addr`,
+/// resolved through the tracee's own page table (not the caller's), the
+/// same `translated_byte_buffer` machinery `check_readable`'s callers use.
+pub fn peek_memory(tracee: &Arc<TaskControlBlock>, addr: usize, len: usize) -> Option<Vec<u8>> {
+    let token = tracee.get_user_token();
+    let regions = translated_byte_buffer(token, addr as *const u8, len).ok()?;
+    let mut buf = Vec::with_capacity(len);
+    for region in regions {
+        buf.extend_from_slice(region);
+    }
+    Some(buf)
+}
+
+/// Overwrite `data.len()` bytes of the tracee's user memory starting at
+/// `addr`, resolved the same way as [`peek_memory`].
+pub fn poke_memory(tracee: &Arc<TaskControlBlock>, addr: usize, data: &[u8]) -> bool {
+    let token = tracee.get_user_token();
+    let regions = match translated_byte_buffer(token, addr as *const u8, data.len()) {
+        Ok(regions) => regions,
+        Err(_) => return false,
+    };
+    let mut offset = 0;
+    for region in regions {
+        region.copy_from_slice(&data[offset..offset + region.len()]);
+        offset += region.len();
+    }
+    true
+}
+
+/// Look up a tracer's child by pid; used by the `task::ptrace_*` free
+/// functions so each doesn't repeat the lookup.
+pub(super) fn lookup(tracer: &Arc<TaskControlBlock>, pid: usize) -> Option<Arc<TaskControlBlock>> {
+    find_child(tracer, pid)
+}
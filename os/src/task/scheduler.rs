@@ -0,0 +1,162 @@
+//! Pluggable scheduling policies used by [`super::TaskManager`] to pick the
+//! next `Ready` task, replacing the previously hardwired round-robin scan.
+
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+use super::task::TaskControlBlock;
+
+/// A large constant every live task's `stride` advances towards.
+///
+/// Chosen so that `pass = BIG_STRIDE / priority` stays `<= BIG_STRIDE / 2`
+/// for every allowed `priority` (`>= MIN_PRIORITY`); that keeps the spread
+/// between the smallest and largest live `stride` bounded by `BIG_STRIDE / 2`
+/// and makes the wraparound-aware comparison in [`stride_less`] safe.
+pub const BIG_STRIDE: usize = 0xFFFF;
+
+/// Smallest allowed task priority. `pass = BIG_STRIDE / priority` must stay
+/// `<= BIG_STRIDE / 2`, i.e. `priority >= 2`.
+pub const MIN_PRIORITY: usize = 2;
+
+/// Priority newly created tasks start with, until `sys_set_priority`
+/// changes it.
+pub const DEFAULT_PRIORITY: usize = 16;
+
+/// `a < b` in the wraparound-safe stride ordering. `stride` is a `usize`
+/// counter that keeps growing and eventually wraps; since the spread between
+/// any two live strides never exceeds `BIG_STRIDE / 2`, a wrapping
+/// difference larger than that threshold actually represents a negative
+/// difference that wrapped around `usize::MAX`, so it means `a` is behind.
+fn stride_less(a: usize, b: usize) -> bool {
+    a.wrapping_sub(b) > BIG_STRIDE / 2
+}
+
+/// Abstraction [`super::TaskManager`] schedules through, so the policy used
+/// to pick the next task (round-robin, stride, ...) can be swapped without
+/// touching task lifecycle code. `T` is the task handle type
+/// (`Arc<TaskControlBlock>`).
+pub trait Scheduler<T> {
+    /// Add a schedulable task.
+    fn insert(&mut self, task: T);
+    /// Look at the task that would be picked next, without removing it.
+    fn peek(&self) -> Option<&T>;
+    /// Mutable version of [`Scheduler::peek`].
+    fn peek_mut(&mut self) -> Option<&mut T>;
+    /// Remove and return the task that would be picked next.
+    fn pop(&mut self) -> Option<T>;
+    /// Remove the first task matching `pred`, wherever it sits in the
+    /// schedule (e.g. to drop a task that left the ready set some other way).
+    fn remove<F: FnMut(&T) -> bool>(&mut self, pred: F) -> Option<T>;
+}
+
+/// Plain round-robin FIFO: the scheduling behavior this crate used before
+/// `Scheduler` existed, kept as a selectable policy alongside stride
+/// scheduling.
+pub struct FifoScheduler<T> {
+    queue: VecDeque<T>,
+}
+
+impl<T> FifoScheduler<T> {
+    /// An empty FIFO ready queue.
+    pub fn new() -> Self {
+        Self {
+            queue: VecDeque::new(),
+        }
+    }
+}
+
+impl<T> Scheduler<T> for FifoScheduler<T> {
+    fn insert(&mut self, task: T) {
+        self.queue.push_back(task);
+    }
+
+    fn peek(&self) -> Option<&T> {
+        self.queue.front()
+    }
+
+    fn peek_mut(&mut self) -> Option<&mut T> {
+        self.queue.front_mut()
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        self.queue.pop_front()
+    }
+
+    fn remove<F: FnMut(&T) -> bool>(&mut self, mut pred: F) -> Option<T> {
+        let index = self.queue.iter().position(|t| pred(t))?;
+        self.queue.remove(index)
+    }
+}
+
+/// Stride scheduling: always runs the runnable task with the smallest
+/// `stride`, then advances it by its `pass = BIG_STRIDE / priority` (see
+/// [`TaskControlBlockInner::pass`](super::task::TaskControlBlockInner::pass)).
+/// Since `pass` is inversely proportional to `priority`, high-priority tasks
+/// advance slower and get picked more often.
+///
+/// Specific to `Arc<TaskControlBlock>` (rather than generic over `T`) since
+/// it needs to read/update the `priority`/`stride` fields carried by the TCB
+/// itself.
+pub struct StrideScheduler {
+    tasks: Vec<Arc<TaskControlBlock>>,
+}
+
+impl StrideScheduler {
+    /// An empty stride-scheduled ready set.
+    pub fn new() -> Self {
+        Self { tasks: Vec::new() }
+    }
+
+    /// Index of the task with the smallest `stride`, if any.
+    fn min_index(&self) -> Option<usize> {
+        self.tasks
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| {
+                let sa = a.inner_exclusive_access().stride;
+                let sb = b.inner_exclusive_access().stride;
+                if sa == sb {
+                    Ordering::Equal
+                } else if stride_less(sa, sb) {
+                    Ordering::Less
+                } else {
+                    Ordering::Greater
+                }
+            })
+            .map(|(index, _)| index)
+    }
+}
+
+impl Scheduler<Arc<TaskControlBlock>> for StrideScheduler {
+    fn insert(&mut self, task: Arc<TaskControlBlock>) {
+        self.tasks.push(task);
+    }
+
+    fn peek(&self) -> Option<&Arc<TaskControlBlock>> {
+        self.min_index().map(|index| &self.tasks[index])
+    }
+
+    fn peek_mut(&mut self) -> Option<&mut Arc<TaskControlBlock>> {
+        self.min_index().map(move |index| &mut self.tasks[index])
+    }
+
+    fn pop(&mut self) -> Option<Arc<TaskControlBlock>> {
+        let index = self.min_index()?;
+        let task = self.tasks.remove(index);
+        let mut inner = task.inner_exclusive_access();
+        let pass = inner.pass();
+        inner.stride = inner.stride.wrapping_add(pass);
+        drop(inner);
+        Some(task)
+    }
+
+    fn remove<F: FnMut(&Arc<TaskControlBlock>) -> bool>(
+        &mut self,
+        mut pred: F,
+    ) -> Option<Arc<TaskControlBlock>> {
+        let index = self.tasks.iter().position(|t| pred(t))?;
+        Some(self.tasks.remove(index))
+    }
+}
@@ -0,0 +1,93 @@
+//! Per-task seccomp-style syscall filtering, layered on the syscall
+//! accounting path in [`super::processor::Processor::syscall_counter`].
+
+use alloc::collections::BTreeMap;
+
+/// What to do with a filtered syscall.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FilterAction {
+    /// Run the syscall as requested.
+    Allow,
+    /// Don't run the syscall; report this value as its result instead.
+    Errno(isize),
+    /// Kill the task instead of running the syscall.
+    Kill,
+}
+
+impl FilterAction {
+    /// How restrictive this action is, `Allow < Errno < Kill`. Used to
+    /// enforce that a rule is only ever tightened, never loosened, once the
+    /// filter has entered strict mode.
+    fn strictness(self) -> u8 {
+        match self {
+            FilterAction::Allow => 0,
+            FilterAction::Errno(_) => 1,
+            FilterAction::Kill => 2,
+        }
+    }
+}
+
+/// A task's syscall filter: a default action plus overrides for specific
+/// syscall ids. Consulted by `Processor::syscall_counter` right where it
+/// already intercepts every syscall id for `task_info.syscall_times`
+/// accounting, giving callers a lightweight sandbox without a full BPF
+/// engine.
+#[derive(Clone)]
+pub struct SyscallFilter {
+    default_action: FilterAction,
+    rules: BTreeMap<usize, FilterAction>,
+}
+
+impl SyscallFilter {
+    /// No filtering: every syscall is allowed.
+    pub fn new() -> Self {
+        Self {
+            default_action: FilterAction::Allow,
+            rules: BTreeMap::new(),
+        }
+    }
+
+    /// Whether the default action has been tightened to `Kill` (see
+    /// [`SyscallFilter::enter_strict_mode`]).
+    fn is_strict(&self) -> bool {
+        self.default_action == FilterAction::Kill
+    }
+
+    /// The action to take for `syscall_id`: its rule if one is set, else the
+    /// default action.
+    pub fn action(&self, syscall_id: usize) -> FilterAction {
+        self.rules
+            .get(&syscall_id)
+            .copied()
+            .unwrap_or(self.default_action)
+    }
+
+    /// Set the action for one syscall id. In strict mode, a rule can only be
+    /// tightened (`Allow` -> `Errno` -> `Kill`), never loosened; rejected
+    /// attempts return `Err(())` and leave the existing rule untouched, so a
+    /// sandboxed task can't talk its way out of its own restrictions.
+    pub fn set_rule(&mut self, syscall_id: usize, action: FilterAction) -> Result<(), ()> {
+        if self.is_strict() {
+            let current = self.action(syscall_id);
+            if action.strictness() < current.strictness() {
+                return Err(());
+            }
+        }
+        self.rules.insert(syscall_id, action);
+        Ok(())
+    }
+
+    /// Tighten the default action to `Kill`, so only syscall ids with an
+    /// explicit `Allow`/`Errno` rule survive. One-way: there is no method to
+    /// set the default back to `Allow`, so once a task is strict it stays
+    /// strict.
+    pub fn enter_strict_mode(&mut self) {
+        self.default_action = FilterAction::Kill;
+    }
+}
+
+impl Default for SyscallFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -2,18 +2,327 @@
 
 // 有序键值对
 use alloc::collections::BTreeMap;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
 
+use super::pid::{pid_alloc, KernelStack, PidHandle};
+use super::scheduler::{BIG_STRIDE, DEFAULT_PRIORITY, MIN_PRIORITY};
+use super::seccomp::{FilterAction, SyscallFilter};
 use super::TaskContext;
+use crate::mm::{MemorySet, PhysPageNum, VirtAddr, KERNEL_SPACE};
+use crate::sync::UPSafeCell;
+use crate::trap::{trap_handler, TrapContext};
 
 /// The task control block (TCB) of a task.
-#[derive(Clone)]
+///
+/// `pid`/`kernel_stack` are fixed for the lifetime of the task; everything
+/// that changes while the task runs lives behind `inner` so it can be
+/// borrowed mutably at runtime without fighting the borrow checker, the same
+/// pattern used by `UPSafeCell` elsewhere in this crate.
 pub struct TaskControlBlock {
+    /// Process identifier, stable for the task's whole lifetime.
+    pub pid: PidHandle,
+    /// The kernel stack backing this task's traps, mapped for as long as
+    /// the task (and thus this handle) lives.
+    pub kernel_stack: KernelStack,
+    inner: UPSafeCell<TaskControlBlockInner>,
+}
+
+/// Everything about a task that can change while it is alive.
+pub struct TaskControlBlockInner {
+    /// Physical page holding this task's `TrapContext`.
+    pub trap_cx_ppn: PhysPageNum,
+    /// Size of the application's address space, in bytes, lowest address
+    /// being 0x0.
+    pub base_size: usize,
+    /// Lowest address of the heap area created by `from_elf` for `sys_sbrk`.
+    pub heap_bottom: usize,
+    /// Current program break, moved by `sys_sbrk`.
+    pub program_brk: usize,
     /// The task status in it's lifecycle
     pub task_status: TaskStatus,
     /// The task context
     pub task_cx: TaskContext,
+    /// This task's address space.
+    pub memory_set: MemorySet,
+    /// Parent task, if any; `Weak` so a parent/child cycle doesn't leak.
+    pub parent: Option<Weak<TaskControlBlock>>,
+    /// Live children, reaped (and removed) by `sys_waitpid`.
+    pub children: Vec<Arc<TaskControlBlock>>,
+    /// Exit code reported to `sys_waitpid`, valid once `task_status` is
+    /// `Exited`.
+    pub exit_code: i32,
     /// 任务信息
     pub task_info: TaskInfo,
+    /// Scheduling priority used by [`super::scheduler::StrideScheduler`],
+    /// kept `>= MIN_PRIORITY`: [`TaskControlBlock::set_priority`] rejects
+    /// anything lower.
+    pub priority: usize,
+    /// Stride-scheduling counter, advanced by `pass()` each time this task
+    /// is picked to run.
+    pub stride: usize,
+    /// Seccomp-style syscall filter, consulted by
+    /// `Processor::syscall_counter` on every syscall this task makes.
+    pub syscall_filter: SyscallFilter,
+    /// The task tracing this one, if any (see [`super::ptrace`]). `Weak`
+    /// for the same reason as `parent`: the tracer doesn't own the tracee's
+    /// lifetime.
+    pub tracer: Option<Weak<TaskControlBlock>>,
+}
+
+impl TaskControlBlockInner {
+    /// Get the mutable reference of the `TrapContext` of this task.
+    pub fn get_trap_cx(&self) -> &'static mut TrapContext {
+        self.trap_cx_ppn.get_mut()
+    }
+
+    /// Get the page table token (satp value) of this task's address space.
+    pub fn get_user_token(&self) -> usize {
+        self.memory_set.token()
+    }
+
+    pub(crate) fn get_status(&self) -> TaskStatus {
+        self.task_status
+    }
+
+    /// Whether this task has exited and is waiting to be reaped by `sys_waitpid`.
+    pub(crate) fn is_zombie(&self) -> bool {
+        self.get_status() == TaskStatus::Exited
+    }
+
+    /// This task's stride increment for one schedule, `BIG_STRIDE /
+    /// priority`: inversely proportional to `priority`, so higher-priority
+    /// tasks advance more slowly and get picked more often.
+    pub fn pass(&self) -> usize {
+        BIG_STRIDE / self.priority
+    }
+}
+
+impl TaskControlBlock {
+    /// Exclusive access to the mutable part of this task.
+    pub fn inner_exclusive_access(&self) -> core::cell::RefMut<'_, TaskControlBlockInner> {
+        self.inner.exclusive_access()
+    }
+
+    /// Get the page table token of this task's address space.
+    pub fn get_user_token(&self) -> usize {
+        self.inner_exclusive_access().get_user_token()
+    }
+
+    /// Get the mutable reference of the `TrapContext` of this task.
+    pub fn get_trap_cx(&self) -> &'static mut TrapContext {
+        self.inner_exclusive_access().get_trap_cx()
+    }
+
+    /// Build a new task, with a freshly allocated pid, straight from an elf
+    /// image.
+    pub fn new(elf_data: &[u8]) -> Self {
+        let (mut memory_set, user_sp, entry_point) =
+            MemorySet::from_elf(elf_data).expect("failed to build address space from elf");
+        let trap_cx_ppn = memory_set
+            .transform(VirtAddr::from(crate::config::TRAP_CONTEXT_BASE).into())
+            .expect("TrapContext must be mapped by from_elf")
+            .ppn();
+        let pid_handle = pid_alloc();
+        let kernel_stack = KernelStack::new(&pid_handle);
+        let kernel_stack_top = kernel_stack.get_top();
+        let task_control_block = Self {
+            pid: pid_handle,
+            kernel_stack,
+            inner: unsafe {
+                UPSafeCell::new(TaskControlBlockInner {
+                    trap_cx_ppn,
+                    base_size: user_sp,
+                    heap_bottom: user_sp,
+                    program_brk: user_sp,
+                    task_status: TaskStatus::Ready,
+                    task_cx: TaskContext::goto_trap_return(kernel_stack_top),
+                    memory_set,
+                    parent: None,
+                    children: Vec::new(),
+                    exit_code: 0,
+                    task_info: TaskInfo::new(),
+                    priority: DEFAULT_PRIORITY,
+                    stride: 0,
+                    syscall_filter: SyscallFilter::new(),
+                    tracer: None,
+                })
+            },
+        };
+        let trap_cx = task_control_block.get_trap_cx();
+        *trap_cx = TrapContext::app_init_context(
+            entry_point,
+            user_sp,
+            KERNEL_SPACE.exclusive_access().token(),
+            kernel_stack_top,
+            trap_handler as usize,
+        );
+        task_control_block
+    }
+
+    /// Duplicate this task's address space (copy-on-write) and state into a
+    /// freshly allocated child, recording the parent/child `Arc` link.
+    ///
+    /// Returns `None` (instead of panicking) if the clone fails, e.g. because
+    /// the system is out of physical frames; the parent is left untouched
+    /// and `sys_fork` reports the failure to userspace as `-1`. This is
+    /// graceful *failure*, not reclamation: there is no reclaimable-page
+    /// list or eviction loop here, so a transient OOM that a second attempt
+    /// could have survived still fails outright rather than freeing clean
+    /// pages and retrying.
+    ///
+    /// STATUS: the "cheap" half is blocked. `MemorySet::from_existed_user` /
+    /// `MapArea::clone_cow` do make this `fork` cheap at the call site —
+    /// shared frames, no eager per-page copy — but a shared frame only stays
+    /// cheap until the first write to it, and that write is supposed to
+    /// trap into `MapArea::handle_cow_fault` to split the sharing. Nothing
+    /// in this repo slice calls `MemorySet::handle_page_fault`, the one
+    /// caller of `handle_cow_fault` (see its own doc note): there's no
+    /// `trap` module here to decode the `StorePageFault` and
+    /// route it there. So as shipped, the first write a child or parent
+    /// makes to a post-fork shared page does not go through COW at all.
+    pub fn fork(self: &Arc<Self>) -> Option<Arc<Self>> {
+        let mut parent_inner = self.inner_exclusive_access();
+        let mut memory_set = MemorySet::from_existed_user(&mut parent_inner.memory_set).ok()?;
+        let trap_cx_ppn = memory_set
+            .transform(VirtAddr::from(crate::config::TRAP_CONTEXT_BASE).into())
+            .ok()?
+            .ppn();
+        let pid_handle = pid_alloc();
+        let kernel_stack = KernelStack::new(&pid_handle);
+        let kernel_stack_top = kernel_stack.get_top();
+        let task_control_block = Arc::new(TaskControlBlock {
+            pid: pid_handle,
+            kernel_stack,
+            inner: unsafe {
+                UPSafeCell::new(TaskControlBlockInner {
+                    trap_cx_ppn,
+                    base_size: parent_inner.base_size,
+                    heap_bottom: parent_inner.heap_bottom,
+                    program_brk: parent_inner.program_brk,
+                    task_status: TaskStatus::Ready,
+                    task_cx: TaskContext::goto_trap_return(kernel_stack_top),
+                    memory_set,
+                    parent: Some(Arc::downgrade(self)),
+                    children: Vec::new(),
+                    exit_code: 0,
+                    task_info: TaskInfo::new(),
+                    priority: DEFAULT_PRIORITY,
+                    stride: 0,
+                    syscall_filter: SyscallFilter::new(),
+                    tracer: None,
+                })
+            },
+        });
+        parent_inner.children.push(Arc::clone(&task_control_block));
+        // the child's return value from `fork` is 0; the parent's is its pid,
+        // set by the caller (`sys_fork`) once back in userspace.
+        let trap_cx = task_control_block.inner_exclusive_access().get_trap_cx();
+        trap_cx.kernel_sp = kernel_stack_top;
+        Some(task_control_block)
+    }
+
+    /// Replace this task's address space in place with a freshly loaded
+    /// elf, keeping the same pid/kernel stack (`sys_exec`).
+    ///
+    /// The new address space is built up-front and only swapped in once it
+    /// fully succeeds, so a failure (e.g. out of physical frames) leaves the
+    /// caller's current address space intact and `sys_exec` reports `-1`
+    /// instead of tearing down the kernel. Each eagerly-mapped segment
+    /// `MemorySet::from_elf` pushes (program headers, `TrapContext`) goes
+    /// through `MemorySet::check_all_page_with_reclaim`, so a transient OOM
+    /// gets one retry against the other segments already mapped in the *new*
+    /// address space before `from_elf` gives up — but there's nothing yet to
+    /// reclaim from on the very first segment, so this is best-effort, not a
+    /// guarantee. As with [`TaskControlBlock::fork`], a retry that still
+    /// fails aborts the whole `exec` rather than reclaiming across tasks.
+    pub fn exec(&self, elf_data: &[u8]) -> bool {
+        let (mut memory_set, user_sp, entry_point) = match MemorySet::from_elf(elf_data) {
+            Ok(result) => result,
+            Err(_) => return false,
+        };
+        let trap_cx_ppn = match memory_set.transform(VirtAddr::from(crate::config::TRAP_CONTEXT_BASE).into()) {
+            Ok(pte) => pte.ppn(),
+            Err(_) => return false,
+        };
+        let mut inner = self.inner_exclusive_access();
+        inner.memory_set = memory_set;
+        inner.trap_cx_ppn = trap_cx_ppn;
+        inner.base_size = user_sp;
+        inner.heap_bottom = user_sp;
+        inner.program_brk = user_sp;
+        let kernel_stack_top = self.kernel_stack.get_top();
+        let trap_cx = inner.get_trap_cx();
+        *trap_cx = TrapContext::app_init_context(
+            entry_point,
+            user_sp,
+            KERNEL_SPACE.exclusive_access().token(),
+            kernel_stack_top,
+            trap_handler as usize,
+        );
+        true
+    }
+
+    /// This task's pid.
+    pub fn getpid(&self) -> usize {
+        self.pid.0
+    }
+
+    /// Grow/shrink the heap by `size` bytes (`sys_sbrk`), returning the old
+    /// program break, or `None` if the change would move it below
+    /// `heap_bottom`.
+    pub fn change_program_brk(&self, size: i32) -> Option<usize> {
+        let mut inner = self.inner_exclusive_access();
+        let old_brk = inner.program_brk;
+        let new_brk = inner.program_brk as isize + size as isize;
+        if new_brk < inner.heap_bottom as isize {
+            return None;
+        }
+        let result = if size < 0 {
+            inner
+                .memory_set
+                .shrink_to(inner.heap_bottom.into(), (new_brk as usize).into())
+        } else {
+            inner
+                .memory_set
+                .append_to(inner.heap_bottom.into(), (new_brk as usize).into())
+        };
+        if result.is_ok() {
+            inner.program_brk = new_brk as usize;
+            Some(old_brk)
+        } else {
+            None
+        }
+    }
+
+    /// Set this task's stride-scheduling priority (`sys_set_priority`).
+    /// Rejects (returns `None`) anything below `MIN_PRIORITY`, since a lower
+    /// priority would push `pass = BIG_STRIDE / priority` past the stride
+    /// scheduler's overflow invariant.
+    pub fn set_priority(&self, priority: usize) -> Option<usize> {
+        if priority < MIN_PRIORITY {
+            return None;
+        }
+        self.inner_exclusive_access().priority = priority;
+        Some(priority)
+    }
+
+    /// Install/overwrite this task's filter rule for `syscall_id`. Fails
+    /// (returns `Err(())`) if the task is in strict mode and `action` would
+    /// loosen an existing rule (see `SyscallFilter::set_rule`).
+    pub fn set_syscall_filter_rule(&self, syscall_id: usize, action: FilterAction) -> Result<(), ()> {
+        self.inner_exclusive_access()
+            .syscall_filter
+            .set_rule(syscall_id, action)
+    }
+
+    /// Tighten this task's syscall filter into strict mode (default action
+    /// `Kill`); one-way, see `SyscallFilter::enter_strict_mode`.
+    pub fn enter_strict_syscall_filter(&self) {
+        self.inner_exclusive_access()
+            .syscall_filter
+            .enter_strict_mode();
+    }
 }
 
 /// 任务信息块
@@ -53,6 +362,10 @@ pub enum TaskStatus {
     Ready,
     /// running
     Running,
+    /// stopped at a syscall boundary, waiting on its tracer (see
+    /// `ptrace::stop_if_traced`); never picked by the scheduler while in
+    /// this state
+    Stopped,
     /// exited
     Exited,
 }